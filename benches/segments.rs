@@ -0,0 +1,97 @@
+//! Compare finding the visible value segments of a signal by scanning every timestamp, as each
+//! frame used to, against the cached [`Index`].
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use dwfv::signaldb::{SignalDB, SignalValue};
+use edgescan::index::Index;
+use std::fmt::Write as _;
+
+/// Number of timestamps in the generated dump.
+const TIMESTAMPS: i64 = 1_000_000;
+
+/// Visible time range, about one screen at the default zoom.
+const VISIBLE: std::ops::Range<i64> = 500_000..500_400;
+
+/// A VCD with a fast clock, a counter that changes every 16 clocks, and a signal that changes
+/// once.
+fn generate_vcd() -> String {
+    let mut vcd = String::from(
+        "$timescale 1 ns $end\n\
+         $scope module top $end\n\
+         $var wire 1 ! clk $end\n\
+         $var wire 8 \" count $end\n\
+         $var wire 1 # reset $end\n\
+         $upscope $end\n\
+         $enddefinitions $end\n",
+    );
+    for time in 0..TIMESTAMPS {
+        writeln!(vcd, "#{time}\n{}!", time % 2).unwrap();
+        if time % 32 == 0 {
+            writeln!(vcd, "b{:b} \"", (time / 32) % 256).unwrap();
+        }
+        if time == 0 || time == 10 {
+            writeln!(vcd, "{}#", (time == 0) as u8).unwrap();
+        }
+    }
+
+    vcd
+}
+
+/// The segments of a signal found by querying its value at every visible timestamp.
+fn scan_segments(db: &SignalDB, id: &str, from: i64, to: i64) -> Vec<(i64, i64, SignalValue)> {
+    let timestamps = db.get_timestamps();
+    let end = timestamps.last().map_or(0, |ts| ts.get_value()) + 1;
+    let first = timestamps
+        .partition_point(|ts| ts.get_value() <= from)
+        .saturating_sub(1);
+    let last = timestamps.partition_point(|ts| ts.get_value() <= to);
+    let end = timestamps.get(last).map_or(end, |ts| ts.get_value());
+
+    let mut segments: Vec<(i64, i64, SignalValue)> = Vec::new();
+    for ts in &timestamps[first..last] {
+        let value = db.value_at(id, *ts).unwrap();
+        if let Some(segment) = segments.last_mut() {
+            if segment.2 == value {
+                continue;
+            }
+            segment.1 = ts.get_value();
+        }
+        segments.push((ts.get_value(), end, value));
+    }
+
+    segments
+}
+
+fn segments(c: &mut Criterion) {
+    let vcd = generate_vcd();
+    let db = SignalDB::from_vcd(vcd.as_bytes()).unwrap();
+    let ids = db.get_signal_ids();
+    let end = TIMESTAMPS + 1;
+
+    let index = Index::new(&db);
+    for id in &ids {
+        index.transitions(&db, id);
+    }
+
+    let mut group = c.benchmark_group("visible segments");
+    group.bench_function("scan", |b| {
+        b.iter(|| {
+            for id in &ids {
+                black_box(scan_segments(&db, id, VISIBLE.start, VISIBLE.end));
+            }
+        })
+    });
+    group.bench_function("index", |b| {
+        b.iter(|| {
+            for id in &ids {
+                for segment in index.segments(&db, id, VISIBLE.start, VISIBLE.end, end) {
+                    black_box(segment);
+                }
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, segments);
+criterion_main!(benches);
@@ -1,6 +1,13 @@
-use directories::ProjectDirs;
+use crate::radix::Radix;
+use directories::{ProjectDirs, UserDirs};
+use log::warn;
 use serde_derive::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::monitor::MonitorHandle;
+use winit::window::Theme as SystemTheme;
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -12,36 +19,174 @@ pub enum Error {
 
     #[error("Failed to serialize config to file")]
     Serialize(#[from] ron::Error),
+
+    #[error("Unable to parse config file `{}`", .0.display())]
+    Parse(PathBuf),
 }
 
+/// Current version of the config file format.
+///
+/// Increment this when the meaning of an existing field changes, and handle the old meaning in
+/// [`ConfigData::migrate`]. New fields do not need a version bump; they take their default value.
+const CONFIG_VERSION: u32 = 1;
+
+/// Maximum number of entries in the recent files list.
+const MAX_RECENT_FILES: usize = 8;
+
+/// Sample counts that can be chosen for anti-aliasing; 1 disables it.
+pub const SAMPLE_COUNTS: [u32; 4] = [1, 2, 4, 8];
+
+/// Size in MiB above which files ask before loading, by default.
+const DEFAULT_LARGE_FILE_SIZE: u64 = 1024;
+
+/// Timestamps offered to load from a large file, by default.
+const DEFAULT_LARGE_FILE_TIMESTAMPS: u64 = 1_000_000;
+
+/// Rows scrolled per mouse wheel step, by default and at most.
+pub const DEFAULT_SCROLL_ROWS: u32 = 3;
+pub const SCROLL_ROWS: RangeInclusive<u32> = 1..=20;
+
+/// Range of custom waveform row heights, in points.
+pub const ROW_HEIGHTS: RangeInclusive<f32> = 8.0..=64.0;
+
+/// Range of waveform line widths, in points.
+pub const LINE_WIDTHS: RangeInclusive<f32> = 1.0..=3.0;
+
 #[derive(Debug)]
 pub struct Config {
     dirs: ProjectDirs,
     data: ConfigData,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Color theme for the UI.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Theme {
+    /// Follow the operating system preference.
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+/// Colors of waveforms and the cursor.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ColorScheme {
+    /// Green waveforms, darker on light backgrounds.
+    #[default]
+    Classic,
+    /// Darker colors, for light backgrounds.
+    Light,
+    HighContrast,
+    /// Colors that can be told apart with the common forms of color blindness.
+    Colorblind,
+}
+
+/// The colors of a [`ColorScheme`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Palette {
+    pub waveform: egui::Color32,
+    /// High-impedance (Z) values.
+    pub highz: egui::Color32,
+    /// Unknown (X) values.
+    pub unknown: egui::Color32,
+    pub cursor: egui::Color32,
+}
+
+/// Graphics API used for rendering.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Backend {
+    /// Let `wgpu` choose among Vulkan, Metal, DX12, and WebGPU.
+    #[default]
+    Primary,
+    Vulkan,
+    Metal,
+    Dx12,
+    Gl,
+}
+
+/// Presentation mode for the window surface, controlling vsync.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum PresentMode {
+    /// Vsync, with adaptive or mailbox presentation where available.
+    #[default]
+    AutoVsync,
+    /// No vsync, with mailbox presentation where available.
+    AutoNoVsync,
+    Fifo,
+    Mailbox,
+}
+
+/// Missing fields take their default values, so older config files remain readable.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
 struct ConfigData {
+    /// Config files written before versioning was introduced have no version, which reads as 0.
+    #[serde(default)]
+    version: u32,
     window_width: u32,
     window_height: u32,
+    window_x: Option<i32>,
+    window_y: Option<i32>,
+    /// Fill the screen. The window size and position are those to restore when leaving it.
+    fullscreen: bool,
+    /// Scale factor the window size was saved at; the size itself is in logical pixels.
+    scale_factor: f64,
+    recent_files: Vec<String>,
+    theme: Theme,
+    backend: Backend,
+    present_mode: PresentMode,
+    last_directory: Option<String>,
+    /// Waveform row height in points, or `None` to fit the signal names.
+    row_height: Option<f32>,
+    /// Show the values at the cursor in their own column, instead of after the signal names.
+    value_column: bool,
+    /// Move the view gradually when zooming to fit or jumping to a time.
+    animate_view: bool,
+    /// Name of the preferred GPU adapter, or `None` to choose one automatically.
+    adapter: Option<String>,
+    /// Samples per pixel for multisample anti-aliasing.
+    sample_count: u32,
+    /// Radix of signals that have not been given one.
+    default_radix: Radix,
+    /// Rows scrolled per mouse wheel step.
+    scroll_rows: u32,
+    /// Width of waveform lines, in points.
+    line_width: f32,
+    color_scheme: ColorScheme,
+    /// Let the windows behind show through where nothing is drawn.
+    transparent_background: bool,
+    /// Reload open files when they change on disk, without asking.
+    auto_reload: bool,
+    /// Open files in the running window instead of starting another instance.
+    single_instance: bool,
+    /// Ask before loading files larger than this many MiB, or never if 0.
+    large_file_size: u64,
+    /// Timestamps to offer loading from a large file instead of the whole file, or 0 to not offer.
+    large_file_timestamps: u64,
 }
 
 impl Config {
     pub fn new() -> Result<Self, Error> {
         let dirs = ProjectDirs::from("org", "KodeWerx", "EdgeScan").ok_or(Error::Dirs)?;
+        let path = dirs.config_dir().join("config.ron");
 
-        let mut path = dirs.config_dir().to_path_buf();
-        path.push("config.ron");
+        let data = match std::fs::read_to_string(&path) {
+            Ok(contents) => ConfigData::load(&contents).unwrap_or_else(|| {
+                // Keep the unreadable file so the user can recover their settings
+                let backup = path.with_extension("ron.bak");
+                warn!(
+                    "Unable to parse config, saving a backup to `{}`",
+                    backup.display()
+                );
+                if let Err(err) = std::fs::copy(&path, &backup) {
+                    warn!("Unable to back up config: {err}");
+                }
 
-        let mut data = if let Ok(contents) = std::fs::read_to_string(path) {
-            ron::from_str(&contents).unwrap_or_default()
-        } else {
-            ConfigData::default()
+                ConfigData::default()
+            }),
+            Err(_) => ConfigData::default(),
         };
 
-        // Do not trust user input: Normalize the configuration data before use.
-        data.normalize();
-
         Ok(Self { dirs, data })
     }
 
@@ -50,39 +195,752 @@ impl Config {
     /// The config file is created if it does not exist, along with all intermediate directories in
     /// the path.
     pub fn save(&self) -> Result<(), Error> {
-        let mut path = self.dirs.config_dir().to_path_buf();
-        std::fs::create_dir_all(&path)?;
-        path.push("config.ron");
+        std::fs::create_dir_all(self.dirs.config_dir())?;
 
         let contents = ron::to_string(&self.data)?;
-        std::fs::write(path, contents)?;
+        std::fs::write(self.path(), contents)?;
 
         Ok(())
     }
 
+    /// Read the config file again, e.g. after it was edited by hand.
+    ///
+    /// The current settings are kept if the file cannot be parsed. A missing file resets all
+    /// settings to their defaults.
+    pub fn reload(&mut self) -> Result<(), Error> {
+        let path = self.path();
+        self.data = match std::fs::read_to_string(&path) {
+            Ok(contents) => ConfigData::load(&contents).ok_or(Error::Parse(path))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => ConfigData::default(),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(())
+    }
+
+    /// Reset all settings to their defaults, and save them.
+    pub fn reset(&mut self) -> Result<(), Error> {
+        self.data = ConfigData::default();
+
+        self.save()
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dirs.config_dir().join("config.ron")
+    }
+
     pub fn get_window_size(&self) -> (u32, u32) {
         (self.data.window_width, self.data.window_height)
     }
 
+    /// Save the window size, given in physical pixels, as a logical size.
+    ///
+    /// The size is ignored if the scale factor is not a positive number, or while the window is
+    /// full screen.
     pub(crate) fn set_window_size(&mut self, width: u32, height: u32, scale_factor: f64) {
-        self.data.window_width = (width as f64 / scale_factor) as u32;
-        self.data.window_height = (height as f64 / scale_factor) as u32;
+        if !self.data.fullscreen {
+            self.data.set_window_size(width, height, scale_factor);
+        }
+    }
+
+    /// Scale factor of the monitor the window size was saved on.
+    pub fn window_scale_factor(&self) -> f64 {
+        self.data.scale_factor
+    }
+
+    /// Clamp the saved window size to the largest surface the GPU supports.
+    ///
+    /// `max_size` is the maximum texture dimension in physical pixels. Returns `true` if the size
+    /// was reduced.
+    pub fn limit_window_size(&mut self, max_size: u32, scale_factor: f64) -> bool {
+        if !is_valid_scale_factor(scale_factor) {
+            return false;
+        }
+
+        let max_size = (max_size as f64 / scale_factor) as u32;
+        let (width, height) = self.get_window_size();
+        if width <= max_size && height <= max_size {
+            return false;
+        }
+
+        self.data.window_width = width.min(max_size);
+        self.data.window_height = height.min(max_size);
+        warn!(
+            "Window size {width}x{height} exceeds the GPU limit, reduced to {}x{}",
+            self.data.window_width, self.data.window_height,
+        );
+
+        true
+    }
+
+    /// Get the window's outer position in physical pixels, if one has been saved.
+    pub fn get_window_position(&self) -> Option<(i32, i32)> {
+        self.data.window_x.zip(self.data.window_y)
+    }
+
+    /// Save the window position. It is ignored while the window is full screen.
+    pub fn set_window_position(&mut self, x: i32, y: i32) {
+        if !self.data.fullscreen {
+            self.data.window_x = Some(x);
+            self.data.window_y = Some(y);
+        }
+    }
+
+    /// Whether the window fills the screen.
+    pub fn fullscreen(&self) -> bool {
+        self.data.fullscreen
+    }
+
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        self.data.fullscreen = fullscreen;
+    }
+
+    /// Recently opened files, most recent first.
+    pub fn recent_files(&self) -> &[String] {
+        &self.data.recent_files
+    }
+
+    /// Move a file to the top of the recent files list.
+    ///
+    /// The file's directory becomes the starting directory for the next file dialog.
+    pub(crate) fn add_recent_file(&mut self, path: &Path) {
+        if let Some(dir) = path.parent().filter(|dir| dir.is_dir()) {
+            self.data.last_directory = Some(dir.display().to_string());
+        }
+
+        let path = path.display().to_string();
+        self.data.recent_files.retain(|recent| *recent != path);
+        self.data.recent_files.insert(0, path);
+        self.data.recent_files.truncate(MAX_RECENT_FILES);
+    }
+
+    /// Directory to start file dialogs in.
+    ///
+    /// This is the directory of the last opened file, or the user's documents or home directory if
+    /// it no longer exists.
+    pub fn last_directory(&self) -> Option<PathBuf> {
+        self.data
+            .last_directory
+            .as_ref()
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_dir())
+            .or_else(|| {
+                let dirs = UserDirs::new()?;
+                let dir = dirs.document_dir().unwrap_or_else(|| dirs.home_dir());
+
+                Some(dir.to_path_buf())
+            })
+    }
+
+    pub fn theme(&self) -> Theme {
+        self.data.theme
+    }
+
+    pub(crate) fn set_theme(&mut self, theme: Theme) {
+        self.data.theme = theme;
+    }
+
+    /// The graphics backend to request from `wgpu`.
+    pub fn backend(&self) -> Backend {
+        self.data.backend
+    }
+
+    pub(crate) fn set_backend(&mut self, backend: Backend) {
+        self.data.backend = backend;
+    }
+
+    /// Name of the preferred GPU adapter, or `None` to choose one automatically.
+    pub fn adapter(&self) -> Option<&str> {
+        self.data.adapter.as_deref()
+    }
+
+    pub(crate) fn set_adapter(&mut self, adapter: Option<String>) {
+        self.data.adapter = adapter;
+    }
+
+    /// Samples per pixel for multisample anti-aliasing; 1 disables it.
+    pub fn sample_count(&self) -> u32 {
+        self.data.sample_count
+    }
+
+    pub(crate) fn set_sample_count(&mut self, sample_count: u32) {
+        self.data.sample_count = sample_count;
+    }
+
+    pub fn present_mode(&self) -> PresentMode {
+        self.data.present_mode
+    }
+
+    pub(crate) fn set_present_mode(&mut self, present_mode: PresentMode) {
+        self.data.present_mode = present_mode;
+    }
+
+    /// Waveform row height in points, or `None` to fit the signal names.
+    pub fn row_height(&self) -> Option<f32> {
+        self.data.row_height
+    }
+
+    pub(crate) fn set_row_height(&mut self, row_height: Option<f32>) {
+        self.data.row_height = row_height;
+    }
+
+    /// Whether the values at the cursor are shown in a column between the names and waveforms.
+    pub fn value_column(&self) -> bool {
+        self.data.value_column
+    }
+
+    pub(crate) fn set_value_column(&mut self, value_column: bool) {
+        self.data.value_column = value_column;
+    }
+
+    /// Whether the view moves gradually when zooming to fit or jumping to a time.
+    pub fn animate_view(&self) -> bool {
+        self.data.animate_view
+    }
+
+    pub(crate) fn set_animate_view(&mut self, animate_view: bool) {
+        self.data.animate_view = animate_view;
+    }
+
+    /// Radix of signals that have not been given one.
+    pub fn default_radix(&self) -> Radix {
+        self.data.default_radix
+    }
+
+    pub(crate) fn set_default_radix(&mut self, radix: Radix) {
+        self.data.default_radix = radix;
+    }
+
+    /// Rows scrolled per mouse wheel step.
+    pub fn scroll_rows(&self) -> u32 {
+        self.data.scroll_rows
+    }
+
+    pub(crate) fn set_scroll_rows(&mut self, scroll_rows: u32) {
+        self.data.scroll_rows = scroll_rows;
+    }
+
+    /// Width of waveform lines, in points.
+    pub fn line_width(&self) -> f32 {
+        self.data.line_width
+    }
+
+    pub(crate) fn set_line_width(&mut self, line_width: f32) {
+        self.data.line_width = line_width;
+    }
+
+    pub fn color_scheme(&self) -> ColorScheme {
+        self.data.color_scheme
+    }
+
+    pub(crate) fn set_color_scheme(&mut self, color_scheme: ColorScheme) {
+        self.data.color_scheme = color_scheme;
+    }
+
+    /// Whether the windows behind show through where nothing is drawn, if the GPU supports it.
+    pub fn transparent_background(&self) -> bool {
+        self.data.transparent_background
+    }
+
+    pub(crate) fn set_transparent_background(&mut self, transparent: bool) {
+        self.data.transparent_background = transparent;
+    }
+
+    /// Whether to reload open files when they change on disk, without asking.
+    pub fn auto_reload(&self) -> bool {
+        self.data.auto_reload
+    }
+
+    pub(crate) fn set_auto_reload(&mut self, auto_reload: bool) {
+        self.data.auto_reload = auto_reload;
+    }
+
+    /// Whether to open files in the running window instead of starting another instance.
+    pub fn single_instance(&self) -> bool {
+        self.data.single_instance
+    }
+
+    pub(crate) fn set_single_instance(&mut self, single_instance: bool) {
+        self.data.single_instance = single_instance;
+    }
+
+    /// Size in MiB above which files ask before loading, or 0 to never ask.
+    pub fn large_file_size(&self) -> u64 {
+        self.data.large_file_size
+    }
+
+    pub(crate) fn set_large_file_size(&mut self, size: u64) {
+        self.data.large_file_size = size;
+    }
+
+    /// Number of timestamps that a large file can be truncated to, or 0 to always load all of it.
+    pub fn large_file_timestamps(&self) -> u64 {
+        self.data.large_file_timestamps
+    }
+
+    pub(crate) fn set_large_file_timestamps(&mut self, timestamps: u64) {
+        self.data.large_file_timestamps = timestamps;
+    }
+
+    /// The file that the running instance writes its port to; see [`crate::instance`].
+    pub fn instance_port_path(&self) -> PathBuf {
+        self.dirs.data_local_dir().join("instance-port")
+    }
+
+    /// Ensure the saved window position is visible on one of the available monitors.
+    ///
+    /// A window saved on a monitor that is no longer connected is clamped to the bounds of the
+    /// primary monitor. If there is no primary monitor, the position is discarded.
+    pub fn normalize_window_position(
+        &mut self,
+        mut monitors: impl Iterator<Item = MonitorHandle>,
+        primary: Option<MonitorHandle>,
+    ) {
+        let (x, y) = match self.get_window_position() {
+            Some(position) => position,
+            None => return,
+        };
+
+        // Negative coordinates are legitimate for monitors left of or above the primary monitor.
+        let visible = monitors.any(|monitor| {
+            let PhysicalPosition { x: left, y: top } = monitor.position();
+            let PhysicalSize { width, height } = monitor.size();
+
+            (left..left + width as i32).contains(&x) && (top..top + height as i32).contains(&y)
+        });
+        if visible {
+            return;
+        }
+
+        let position = primary.map(|monitor| {
+            let PhysicalPosition { x: left, y: top } = monitor.position();
+            let PhysicalSize { width, height } = monitor.size();
+            let scale_factor = monitor.scale_factor();
+            let window_width = (self.data.window_width as f64 * scale_factor) as i32;
+            let window_height = (self.data.window_height as f64 * scale_factor) as i32;
+            let right = (left + width as i32 - window_width).max(left);
+            let bottom = (top + height as i32 - window_height).max(top);
+
+            (x.clamp(left, right), y.clamp(top, bottom))
+        });
+        self.data.window_x = position.map(|(x, _)| x);
+        self.data.window_y = position.map(|(_, y)| y);
     }
 }
 
 impl Default for ConfigData {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             window_width: 1200,
             window_height: 800,
+            window_x: None,
+            window_y: None,
+            fullscreen: false,
+            scale_factor: 1.0,
+            recent_files: Vec::new(),
+            theme: Theme::default(),
+            backend: Backend::default(),
+            present_mode: PresentMode::default(),
+            last_directory: None,
+            row_height: None,
+            value_column: false,
+            animate_view: true,
+            adapter: None,
+            sample_count: 1,
+            default_radix: Radix::default(),
+            scroll_rows: DEFAULT_SCROLL_ROWS,
+            line_width: 1.0,
+            color_scheme: ColorScheme::default(),
+            transparent_background: false,
+            auto_reload: false,
+            single_instance: true,
+            large_file_size: DEFAULT_LARGE_FILE_SIZE,
+            large_file_timestamps: DEFAULT_LARGE_FILE_TIMESTAMPS,
         }
     }
 }
 
 impl ConfigData {
+    /// Read the contents of a config file.
+    ///
+    /// Returns `None` if the file is not valid RON at all.
+    fn load(contents: &str) -> Option<Self> {
+        let mut data = Self::parse(contents)?;
+
+        // Do not trust user input: Normalize the configuration data before use.
+        data.migrate();
+        data.normalize();
+
+        Some(data)
+    }
+
+    /// Parse a config file, keeping every field that can be read.
+    ///
+    /// Fields with invalid values are dropped one by one and take their default values, instead of
+    /// discarding the whole file. Returns `None` if the file is not valid RON at all.
+    fn parse(contents: &str) -> Option<Self> {
+        let error = match ron::from_str(contents) {
+            Ok(data) => return Some(data),
+            Err(err) => err,
+        };
+
+        let fields = match ron::from_str::<ron::Value>(contents).ok()? {
+            ron::Value::Map(fields) => fields,
+            _ => return None,
+        };
+        warn!("Config contains invalid fields: {error}");
+
+        let mut valid = ron::Map::new();
+        for (key, value) in fields {
+            let mut candidate = valid.clone();
+            candidate.insert(key.clone(), value);
+            if ron::Value::Map(candidate.clone())
+                .into_rust::<Self>()
+                .is_ok()
+            {
+                valid = candidate;
+            } else {
+                warn!("Resetting config field {key:?} to its default");
+            }
+        }
+
+        ron::Value::Map(valid).into_rust().ok()
+    }
+
+    /// Upgrade config data written by an older version.
+    fn migrate(&mut self) {
+        if self.version < CONFIG_VERSION {
+            // Version 0 only differs by missing fields, which have already taken their defaults.
+            self.version = CONFIG_VERSION;
+        }
+    }
+
+    fn set_window_size(&mut self, width: u32, height: u32, scale_factor: f64) {
+        if !is_valid_scale_factor(scale_factor) {
+            warn!("Ignoring window size with invalid scale factor {scale_factor}");
+            return;
+        }
+
+        // Round to the nearest pixel, so the size survives a round trip through the scale factor
+        self.window_width = (width as f64 / scale_factor).round() as u32;
+        self.window_height = (height as f64 / scale_factor).round() as u32;
+        self.scale_factor = scale_factor;
+    }
+
     fn normalize(&mut self) {
-        // TODO: Max might be more than the `wgpu` adapter supports.
+        // The GPU may support less than this; see `Config::limit_window_size`.
         self.window_width = self.window_width.clamp(400, 10000);
         self.window_height = self.window_height.clamp(400, 10000);
+        if !is_valid_scale_factor(self.scale_factor) {
+            self.scale_factor = 1.0;
+        }
+        if !SAMPLE_COUNTS.contains(&self.sample_count) {
+            self.sample_count = 1;
+        }
+        self.scroll_rows = self
+            .scroll_rows
+            .clamp(*SCROLL_ROWS.start(), *SCROLL_ROWS.end());
+        self.line_width = if self.line_width.is_finite() {
+            self.line_width
+                .clamp(*LINE_WIDTHS.start(), *LINE_WIDTHS.end())
+        } else {
+            1.0
+        };
+        self.recent_files.truncate(MAX_RECENT_FILES);
+        self.row_height = self
+            .row_height
+            .filter(|height| height.is_finite())
+            .map(|height| height.clamp(*ROW_HEIGHTS.start(), *ROW_HEIGHTS.end()));
+    }
+}
+
+/// Scale factors are positive and finite; anything else would corrupt the saved window size.
+fn is_valid_scale_factor(scale_factor: f64) -> bool {
+    scale_factor.is_finite() && scale_factor > 0.0
+}
+
+impl Theme {
+    pub const ALL: [Self; 3] = [Self::System, Self::Light, Self::Dark];
+
+    /// Get the egui visuals for this theme.
+    ///
+    /// The system theme is dark when the OS preference is unknown.
+    pub fn visuals(self, system: Option<SystemTheme>) -> egui::Visuals {
+        match (self, system) {
+            (Self::Light, _) | (Self::System, Some(SystemTheme::Light)) => egui::Visuals::light(),
+            _ => egui::Visuals::dark(),
+        }
+    }
+}
+
+impl ColorScheme {
+    pub const ALL: [Self; 4] = [
+        Self::Classic,
+        Self::Light,
+        Self::HighContrast,
+        Self::Colorblind,
+    ];
+
+    /// The colors of this scheme on a dark or light background.
+    ///
+    /// Schemes other than [`ColorScheme::Light`] have darker colors on light backgrounds, so the
+    /// waveforms stay readable when the theme follows the system.
+    pub fn palette(self, dark_mode: bool) -> Palette {
+        use egui::Color32;
+
+        match (self, dark_mode) {
+            (Self::Classic, true) => Palette {
+                waveform: Color32::GREEN,
+                highz: Color32::GOLD,
+                unknown: Color32::RED,
+                cursor: Color32::YELLOW,
+            },
+            (Self::Classic, false) | (Self::Light, _) => Palette {
+                waveform: Color32::DARK_GREEN,
+                highz: Color32::from_rgb(176, 112, 0),
+                unknown: Color32::DARK_RED,
+                cursor: Color32::from_rgb(224, 96, 0),
+            },
+            (Self::HighContrast, true) => Palette {
+                waveform: Color32::WHITE,
+                highz: Color32::YELLOW,
+                unknown: Color32::from_rgb(255, 0, 255),
+                cursor: Color32::from_rgb(0, 255, 255),
+            },
+            (Self::HighContrast, false) => Palette {
+                waveform: Color32::BLACK,
+                highz: Color32::from_rgb(160, 96, 0),
+                unknown: Color32::from_rgb(192, 0, 192),
+                cursor: Color32::from_rgb(0, 112, 160),
+            },
+            // The Okabe-Ito palette
+            (Self::Colorblind, true) => Palette {
+                waveform: Color32::from_rgb(86, 180, 233),
+                highz: Color32::from_rgb(230, 159, 0),
+                unknown: Color32::from_rgb(213, 94, 0),
+                cursor: Color32::from_rgb(240, 228, 66),
+            },
+            (Self::Colorblind, false) => Palette {
+                waveform: Color32::from_rgb(0, 114, 178),
+                highz: Color32::from_rgb(230, 159, 0),
+                unknown: Color32::from_rgb(213, 94, 0),
+                cursor: Color32::from_rgb(204, 121, 167),
+            },
+        }
+    }
+}
+
+impl Backend {
+    pub const ALL: [Self; 5] = [
+        Self::Primary,
+        Self::Vulkan,
+        Self::Metal,
+        Self::Dx12,
+        Self::Gl,
+    ];
+
+    /// Get the `wgpu` backend bits for this backend.
+    pub fn backends(self) -> wgpu::Backends {
+        match self {
+            Self::Primary => wgpu::Backends::PRIMARY,
+            Self::Vulkan => wgpu::Backends::VULKAN,
+            Self::Metal => wgpu::Backends::METAL,
+            Self::Dx12 => wgpu::Backends::DX12,
+            Self::Gl => wgpu::Backends::GL,
+        }
+    }
+}
+
+impl PresentMode {
+    pub const ALL: [Self; 4] = [
+        Self::AutoVsync,
+        Self::AutoNoVsync,
+        Self::Fifo,
+        Self::Mailbox,
+    ];
+
+    /// Get the `wgpu` present mode.
+    pub fn present_mode(self) -> wgpu::PresentMode {
+        match self {
+            Self::AutoVsync => wgpu::PresentMode::AutoVsync,
+            Self::AutoNoVsync => wgpu::PresentMode::AutoNoVsync,
+            Self::Fifo => wgpu::PresentMode::Fifo,
+            Self::Mailbox => wgpu::PresentMode::Mailbox,
+        }
+    }
+}
+
+impl std::fmt::Display for PresentMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::AutoVsync => "Auto (V-Sync)",
+            Self::AutoNoVsync => "Auto (No V-Sync)",
+            Self::Fifo => "FIFO",
+            Self::Mailbox => "Mailbox",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+impl std::fmt::Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Primary => "Automatic",
+            Self::Vulkan => "Vulkan",
+            Self::Metal => "Metal",
+            Self::Dx12 => "DirectX 12",
+            Self::Gl => "OpenGL",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+impl std::fmt::Display for ColorScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Classic => "Classic",
+            Self::Light => "Light",
+            Self::HighContrast => "High Contrast",
+            Self::Colorblind => "Colorblind Friendly",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+impl std::fmt::Display for Theme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::System => "System",
+            Self::Light => "Light",
+            Self::Dark => "Dark",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_size_is_clamped() {
+        let sizes = [
+            ((0, 0), (400, 400)),
+            ((399, 10001), (400, 10000)),
+            ((1200, 800), (1200, 800)),
+            ((u32::MAX, 400), (10000, 400)),
+        ];
+
+        for ((width, height), expected) in sizes {
+            let mut data = ConfigData {
+                window_width: width,
+                window_height: height,
+                ..ConfigData::default()
+            };
+            data.normalize();
+
+            assert_eq!((data.window_width, data.window_height), expected);
+        }
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        let data = ConfigData {
+            window_width: 1600,
+            window_height: 900,
+            window_x: Some(-100),
+            window_y: Some(50),
+            recent_files: vec!["a.vcd".to_string(), "b.fst".to_string()],
+            theme: Theme::Light,
+            backend: Backend::Vulkan,
+            present_mode: PresentMode::Mailbox,
+            last_directory: Some("/tmp".to_string()),
+            row_height: Some(20.0),
+            adapter: Some("GPU".to_string()),
+            sample_count: 4,
+            default_radix: Radix::Binary,
+            scroll_rows: 5,
+            auto_reload: true,
+            single_instance: false,
+            ..ConfigData::default()
+        };
+        let contents = ron::to_string(&data).unwrap();
+
+        assert_eq!(ConfigData::load(&contents), Some(data));
+    }
+
+    #[test]
+    fn unparseable_config_is_rejected() {
+        assert_eq!(ConfigData::load("not a config"), None);
+        assert_eq!(ConfigData::load("(window_width: "), None);
+    }
+
+    #[test]
+    fn missing_and_invalid_fields_take_defaults() {
+        let data = ConfigData::load("(window_width: 1600, theme: Purple)").unwrap();
+
+        assert_eq!(
+            data,
+            ConfigData {
+                window_width: 1600,
+                ..ConfigData::default()
+            },
+        );
+    }
+
+    #[test]
+    fn window_size_round_trip() {
+        let sizes = [
+            (400, 400),
+            (1200, 800),
+            (1920, 1080),
+            (2561, 1441),
+            (10000, 10000),
+        ];
+        let scale_factors = [0.75, 1.0, 1.1, 1.25, 1.5, 1.75, 2.0, 2.25, 3.0];
+
+        for (width, height) in sizes {
+            for scale_factor in scale_factors {
+                let physical_width = (width as f64 * scale_factor).round() as u32;
+                let physical_height = (height as f64 * scale_factor).round() as u32;
+
+                let mut data = ConfigData::default();
+                data.set_window_size(physical_width, physical_height, scale_factor);
+
+                assert_eq!(
+                    (data.window_width, data.window_height),
+                    (width, height),
+                    "scale factor {scale_factor}",
+                );
+                assert_eq!(data.scale_factor, scale_factor);
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_scale_factor_is_ignored() {
+        for scale_factor in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let mut data = ConfigData::default();
+            data.set_window_size(1920, 1080, scale_factor);
+
+            assert_eq!((data.window_width, data.window_height), (1200, 800));
+            assert_eq!(data.scale_factor, 1.0);
+        }
+    }
+
+    #[test]
+    fn invalid_saved_scale_factor_is_reset() {
+        let mut data = ConfigData {
+            scale_factor: f64::NAN,
+            ..ConfigData::default()
+        };
+        data.normalize();
+
+        assert_eq!(data.scale_factor, 1.0);
     }
 }
@@ -1,10 +1,31 @@
-use crate::gpu::{Error, Gpu};
-use crate::{config::Config, gui::Gui};
+use crate::gpu::{Capture, CaptureError, Error, Gpu};
+use crate::{config::Config, gui, gui::Gui, gui::Snapshot, svg, waveform::Waveform};
 use egui::{ClippedPrimitive, Context, TexturesDelta};
 use egui_wgpu::renderer::{Renderer, ScreenDescriptor};
 use egui_winit::EventResponse;
-use std::time::Duration;
-use winit::{dpi::PhysicalSize, event_loop::EventLoopWindowTarget, window::Window};
+use log::{info, warn};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use winit::{
+    dpi::PhysicalSize,
+    event::WindowEvent,
+    event_loop::{EventLoopProxy, EventLoopWindowTarget},
+    window::{Theme, Window},
+};
+
+/// Number of frames kept for the frame time overlay.
+const FRAME_HISTORY: usize = 120;
+
+/// Events sent to the event loop from other threads.
+#[derive(Debug)]
+pub enum UserEvent {
+    /// Another instance was given a file to open.
+    Open(PathBuf),
+
+    /// A watched file has changed, and the GUI should check which.
+    FileChanged,
+}
 
 /// Manages all state required for rendering egui.
 pub struct Framework {
@@ -22,6 +43,20 @@ pub struct Framework {
 
     // State for the GUI.
     gui: Gui,
+
+    // Timings of recent frames, and the time spent preparing the frame being rendered.
+    frame_times: VecDeque<FrameTime>,
+    prepare_time: Duration,
+}
+
+/// How long a frame took, and when it was presented.
+#[derive(Clone, Copy)]
+struct FrameTime {
+    /// Running egui and tessellating.
+    prepare: Duration,
+    /// Uploading, recording and submitting to the GPU.
+    render: Duration,
+    presented: Instant,
 }
 
 impl Framework {
@@ -29,8 +64,11 @@ impl Framework {
         event_loop: &EventLoopWindowTarget<T>,
         size: PhysicalSize<u32>,
         scale_factor: f64,
+        system_theme: Option<Theme>,
         config: Config,
         gpu: Gpu,
+        path: Option<PathBuf>,
+        proxy: EventLoopProxy<UserEvent>,
     ) -> Self {
         let width = size.width;
         let height = size.height;
@@ -38,6 +76,7 @@ impl Framework {
         let max_texture_size = gpu.device.limits().max_texture_dimension_2d as usize;
 
         let egui_ctx = Context::default();
+        egui_ctx.set_visuals(config.theme().visuals(system_theme));
         let mut egui_state = egui_winit::State::new(event_loop);
         egui_state.set_max_texture_side(max_texture_size);
         egui_state.set_pixels_per_point(scale_factor);
@@ -46,8 +85,8 @@ impl Framework {
             size_in_pixels: [width, height],
             pixels_per_point: scale_factor,
         };
-        let renderer = Renderer::new(&gpu.device, gpu.texture_format, None, 1);
-        let gui = Gui::new();
+        let renderer = Renderer::new(&gpu.device, gpu.texture_format, None, gpu.sample_count());
+        let gui = Gui::new(path, &gpu, proxy);
 
         Self {
             egui_ctx,
@@ -59,6 +98,8 @@ impl Framework {
             gpu,
             config,
             gui,
+            frame_times: VecDeque::with_capacity(FRAME_HISTORY),
+            prepare_time: Duration::ZERO,
         }
     }
 
@@ -66,17 +107,47 @@ impl Framework {
         &mut self.config
     }
 
+    /// Whether the user asked to quit from the GUI.
+    pub fn quit_requested(&self) -> bool {
+        self.gui.quit_requested()
+    }
+
+    /// Save the view of every open file.
+    pub fn save_sessions(&self) {
+        self.gui.save_sessions();
+    }
+
+    /// Open a file sent by another instance.
+    pub fn open_file(&mut self, path: PathBuf) {
+        self.gui.open_file(path);
+    }
+
     /// Handle input events from the window manager.
-    pub fn handle_event(&mut self, event: &winit::event::WindowEvent) -> EventResponse {
+    pub fn handle_event(&mut self, event: &WindowEvent) -> EventResponse {
+        match event {
+            WindowEvent::DroppedFile(path) => self.gui.drop_file(path.clone()),
+            WindowEvent::HoveredFile(_) => self.gui.set_file_hovered(true),
+            WindowEvent::HoveredFileCancelled => self.gui.set_file_hovered(false),
+            WindowEvent::ThemeChanged(theme) => {
+                let visuals = self.config.theme().visuals(Some(*theme));
+                self.egui_ctx.set_visuals(visuals);
+            }
+            _ => (),
+        }
+
         self.egui_state.on_event(&self.egui_ctx, event)
     }
 
     /// Resize egui.
+    ///
+    /// This is also called when the window moves to a monitor with a different scale factor, so
+    /// text and waveforms are drawn at the new resolution.
     pub fn resize(&mut self, window_size: PhysicalSize<u32>, scale_factor: f64) {
         let PhysicalSize { width, height } = window_size;
         if width > 0 && height > 0 {
             self.gpu.resize(window_size);
             self.config.set_window_size(width, height, scale_factor);
+            self.egui_state.set_pixels_per_point(scale_factor as f32);
             self.screen_descriptor.size_in_pixels = [width, height];
             self.screen_descriptor.pixels_per_point = scale_factor as f32;
         }
@@ -84,24 +155,66 @@ impl Framework {
 
     /// Prepare egui.
     pub fn prepare(&mut self, window: &Window) -> Duration {
+        let start = Instant::now();
+
+        // Panels are not filled on a transparent surface, so the windows behind show through
+        if self.gpu.is_transparent() && self.egui_ctx.style().visuals.panel_fill.a() > 0 {
+            let mut style = (*self.egui_ctx.style()).clone();
+            style.visuals.panel_fill = egui::Color32::TRANSPARENT;
+            self.egui_ctx.set_style(style);
+        }
+
         // Run the egui frame and create all paint jobs to prepare for rendering.
         let raw_input = self.egui_state.take_egui_input(window);
         let output = self.egui_ctx.run(raw_input, |egui_ctx| {
             // Draw the demo application.
-            self.gui.ui(egui_ctx, window);
+            self.gui.ui(egui_ctx, window, &mut self.config);
+
+            if self.gui.frame_times_visible() {
+                draw_frame_times(egui_ctx, &self.frame_times);
+            }
         });
 
+        // Apply settings changed in the GUI
+        self.gpu
+            .set_present_mode(self.config.present_mode().present_mode());
+
         self.egui_state
             .handle_platform_output(window, &self.egui_ctx, output.platform_output);
 
+        // Save the shapes of this frame when an SVG export was requested
+        if let Some((path, area)) = self.gui.take_svg_capture() {
+            match svg::save(&path, &output.shapes, area) {
+                Ok(()) => info!("Saved SVG to `{}`", path.display()),
+                Err(err) => gui::show_error(err),
+            }
+        }
+
         self.clipped_primitives = self.egui_ctx.tessellate(output.shapes);
         self.textures_delta = output.textures_delta;
+        self.prepare_time = start.elapsed();
+
+        // The renderer is recreated after tessellating, so this frame is skipped instead of
+        // drawing with textures that are gone. The next frame uploads them again.
+        if self.gpu.set_sample_count(self.config.sample_count()) {
+            self.recreate_renderer();
+            return Duration::ZERO;
+        }
 
         output.repaint_after
     }
 
     pub fn render(&mut self) -> Result<(), Error> {
-        let (mut encoder, frame) = self.gpu.prepare()?;
+        let start = Instant::now();
+        let (mut encoder, frame) = match self.gpu.prepare() {
+            // The surface is still lost after reconfiguring it, so the device is probably gone
+            Err(Error::Surface(wgpu::SurfaceError::Lost)) => {
+                warn!("GPU surface lost, recreating the device");
+                self.recreate_device()?;
+                self.gpu.prepare()?
+            }
+            result => result?,
+        };
         let view = frame
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
@@ -120,26 +233,16 @@ impl Framework {
         );
 
         // Render egui with WGPU
-        {
-            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("egui"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
+        self.paint(&mut encoder, &view);
 
-            self.renderer.render(
-                &mut rpass,
-                &self.clipped_primitives,
-                &self.screen_descriptor,
-            );
-        }
+        // Render the same frame offscreen when an image export was requested
+        let capture = self.gui.take_capture().map(|path| {
+            let capture = Capture::new(&self.gpu, self.screen_descriptor.size_in_pixels);
+            self.paint(&mut encoder, &capture.view);
+            capture.copy(&mut encoder);
+
+            (path, capture)
+        });
 
         // Cleanup
         let textures = std::mem::take(&mut self.textures_delta);
@@ -151,6 +254,203 @@ impl Framework {
         self.gpu.queue.submit(Some(encoder.finish()));
         frame.present();
 
+        if self.frame_times.len() == FRAME_HISTORY {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(FrameTime {
+            prepare: self.prepare_time,
+            render: start.elapsed(),
+            presented: Instant::now(),
+        });
+
+        if let Some((path, capture)) = capture {
+            match capture.save(&self.gpu.device, &path) {
+                Ok(()) => info!("Saved image to `{}`", path.display()),
+                Err(err) => gui::show_error(err),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Recreate the device and all GPU resources owned by egui.
+    fn recreate_device(&mut self) -> Result<(), Error> {
+        self.gpu.recreate_device()?;
+        self.recreate_renderer();
+
         Ok(())
     }
+
+    /// Recreate the renderer, e.g. for a new device or sample count, along with its textures.
+    fn recreate_renderer(&mut self) {
+        self.renderer = Renderer::new(
+            &self.gpu.device,
+            self.gpu.texture_format,
+            None,
+            self.gpu.sample_count(),
+        );
+
+        // The old textures are gone; resetting the fonts uploads the font atlas again
+        self.egui_ctx.set_fonts(egui::FontDefinitions::default());
+        self.egui_ctx.request_repaint();
+
+        // The prepared frame refers to textures that no longer exist
+        self.textures_delta = TexturesDelta::default();
+        self.clipped_primitives.clear();
+    }
+
+    /// Record a render pass drawing egui to the texture view.
+    ///
+    /// With anti-aliasing, egui is drawn to the multisampled target and resolved to the view.
+    fn paint(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let (view, resolve_target) = match self.gpu.msaa_view() {
+            Some(msaa_view) => (msaa_view, Some(view)),
+            None => (view, None),
+        };
+        let clear_color = if self.gpu.is_transparent() {
+            wgpu::Color::TRANSPARENT
+        } else {
+            clear_color(&self.egui_ctx)
+        };
+        paint(
+            &self.renderer,
+            encoder,
+            (view, resolve_target),
+            clear_color,
+            &self.clipped_primitives,
+            &self.screen_descriptor,
+        );
+    }
+}
+
+/// Draw the timings of the most recent frame and the frame rate in the top right corner.
+///
+/// The frame rate is averaged over the recorded frames. It drops while nothing is changing, since
+/// frames are only drawn when needed.
+fn draw_frame_times(ctx: &Context, frame_times: &VecDeque<FrameTime>) {
+    let (last, first) = match (frame_times.back(), frame_times.front()) {
+        (Some(last), Some(first)) => (last, first),
+        _ => return,
+    };
+    let elapsed = last.presented - first.presented;
+    let fps = if elapsed.is_zero() {
+        0.0
+    } else {
+        (frame_times.len() - 1) as f64 / elapsed.as_secs_f64()
+    };
+    let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+
+    egui::Area::new("frame_times")
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-8.0, 32.0))
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.monospace(format!("Prepare: {:6.2} ms", ms(last.prepare)));
+                ui.monospace(format!("Render:  {:6.2} ms", ms(last.render)));
+                ui.monospace(format!("FPS:     {fps:6.1}"));
+            });
+        });
+}
+
+/// Render a single frame of a waveform file to a PNG, without a window.
+///
+/// All signals are displayed, zoomed to fit the time range from `from` to `to`. Missing ends of the
+/// range default to the first and last timestamps.
+pub fn render_to_file(
+    gpu: &Gpu,
+    path: PathBuf,
+    vcd: Waveform,
+    [width, height]: [u32; 2],
+    (from, to): (Option<i64>, Option<i64>),
+    output: &Path,
+) -> Result<(), CaptureError> {
+    let egui_ctx = Context::default();
+    let raw_input = egui::RawInput {
+        screen_rect: Some(egui::Rect::from_min_size(
+            egui::Pos2::ZERO,
+            egui::vec2(width as f32, height as f32),
+        )),
+        pixels_per_point: Some(1.0),
+        max_texture_side: Some(gpu.max_texture_size() as usize),
+        ..Default::default()
+    };
+    let mut snapshot = Snapshot::new(path, vcd, from, to);
+    let output_frame = egui_ctx.run(raw_input, |egui_ctx| snapshot.ui(egui_ctx));
+    let clipped_primitives = egui_ctx.tessellate(output_frame.shapes);
+    let screen_descriptor = ScreenDescriptor {
+        size_in_pixels: [width, height],
+        pixels_per_point: 1.0,
+    };
+
+    let mut renderer = Renderer::new(&gpu.device, gpu.texture_format, None, 1);
+    let mut encoder = gpu
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("headless_command_encoder"),
+        });
+    for (id, image_delta) in &output_frame.textures_delta.set {
+        renderer.update_texture(&gpu.device, &gpu.queue, *id, image_delta);
+    }
+    renderer.update_buffers(
+        &gpu.device,
+        &gpu.queue,
+        &mut encoder,
+        &clipped_primitives,
+        &screen_descriptor,
+    );
+
+    let capture = Capture::new(gpu, [width, height]);
+    paint(
+        &renderer,
+        &mut encoder,
+        (&capture.view, None),
+        clear_color(&egui_ctx),
+        &clipped_primitives,
+        &screen_descriptor,
+    );
+    capture.copy(&mut encoder);
+    gpu.queue.submit(Some(encoder.finish()));
+
+    capture.save(&gpu.device, output)
+}
+
+/// The background color of the theme, which shows wherever egui draws nothing.
+///
+/// The surface is sRGB, so the clear color is given in linear space.
+fn clear_color(egui_ctx: &Context) -> wgpu::Color {
+    let [r, g, b, a] = egui::Rgba::from(egui_ctx.style().visuals.panel_fill).to_array();
+
+    wgpu::Color {
+        r: r as f64,
+        g: g as f64,
+        b: b as f64,
+        a: a as f64,
+    }
+}
+
+/// Record a render pass drawing the primitives to the texture view, after clearing it.
+///
+/// A multisampled view is resolved to the second view of `views`.
+fn paint(
+    renderer: &Renderer,
+    encoder: &mut wgpu::CommandEncoder,
+    (view, resolve_target): (&wgpu::TextureView, Option<&wgpu::TextureView>),
+    clear_color: wgpu::Color,
+    clipped_primitives: &[ClippedPrimitive],
+    screen_descriptor: &ScreenDescriptor,
+) {
+    let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("egui"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(clear_color),
+                store: true,
+            },
+        })],
+        depth_stencil_attachment: None,
+    });
+
+    renderer.render(&mut rpass, clipped_primitives, screen_descriptor);
 }
@@ -0,0 +1,259 @@
+//! Support for the Fast Signal Trace (FST) waveform format.
+//!
+//! FST files are read into an [`FstSource`], which answers the same queries as the [`SignalDB`]
+//! that VCD files are parsed into, so the viewer doesn't care which format a waveform came from.
+//!
+//! [`SignalDB`]: dwfv::signaldb::SignalDB
+
+use crate::waveform::{Header, WaveformSource};
+use dwfv::signaldb::{BitValue, SignalValue, Timestamp};
+use fst_native::{FstFilter, FstHierarchyEntry, FstReader, FstSignalValue};
+use std::collections::HashMap;
+use std::io::{BufRead, Seek};
+use std::path::Path;
+
+pub use fst_native::ReaderError as Error;
+
+/// The first byte of an FST file is the header block type.
+const FST_BL_HDR: u8 = 0;
+
+/// Length of the header block, which follows its type as a big-endian `u64`.
+const HEADER_LENGTH: u64 = 329;
+
+/// A double written after the start and end times in the header, to detect its byte order.
+const ENDIAN_TEST: f64 = std::f64::consts::E;
+
+/// Offset of [`ENDIAN_TEST`] in the file: after the block type, length, start time and end time.
+const ENDIAN_TEST_OFFSET: usize = 1 + 8 + 8 + 8;
+
+/// Signals and value changes read from an FST file.
+#[derive(Debug, Default)]
+pub struct FstSource {
+    // Full dotted name of each signal, and the index of the handle it was recorded with. Signals
+    // that alias each other share a handle.
+    signals: Vec<(String, usize)>,
+
+    // Position in `signals` of each signal ID.
+    ids: HashMap<String, usize>,
+
+    timestamps: Vec<Timestamp>,
+
+    // Value changes of each handle, in time order.
+    changes: Vec<Vec<(i64, SignalValue)>>,
+}
+
+/// Check whether a file is FST by its extension or the start of its header block.
+///
+/// Any binary file may begin with a NUL byte, so the length of the header block and the byte order
+/// test in it must match too.
+pub fn is_fst(path: &Path, magic: &[u8]) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("fst"))
+        || is_header(magic).unwrap_or(false)
+}
+
+/// Check whether `magic` begins with an FST header block, or `None` if it is too short to tell.
+fn is_header(magic: &[u8]) -> Option<bool> {
+    let length = u64::from_be_bytes(magic.get(1..9)?.try_into().ok()?);
+    let test = magic.get(ENDIAN_TEST_OFFSET..ENDIAN_TEST_OFFSET + 8)?;
+    let test: [u8; 8] = test.try_into().ok()?;
+    let endian_test =
+        f64::from_le_bytes(test) == ENDIAN_TEST || f64::from_be_bytes(test) == ENDIAN_TEST;
+
+    Some(magic[0] == FST_BL_HDR && length == HEADER_LENGTH && endian_test)
+}
+
+/// Read an FST file, keeping at most `limit` timestamps.
+///
+/// The header is translated to the declarations a VCD would have.
+pub fn read<R: BufRead + Seek>(input: R, limit: Option<u64>) -> Result<(FstSource, Header), Error> {
+    let mut reader = FstReader::open(input)?;
+    let fst_header = reader.get_header();
+    let header = Header {
+        date: Some(fst_header.date.trim().to_string()).filter(|date| !date.is_empty()),
+        version: Some(fst_header.version.trim().to_string()).filter(|version| !version.is_empty()),
+        timescale: Some(timescale(fst_header.timescale_exponent)),
+    };
+
+    let mut source = FstSource::default();
+    let mut scopes = Vec::new();
+    reader.read_hierarchy(|entry| match entry {
+        FstHierarchyEntry::Scope { name, .. } => scopes.push(name.to_string()),
+        FstHierarchyEntry::UpScope => {
+            scopes.pop();
+        }
+        FstHierarchyEntry::Var { name, handle, .. } => {
+            let index = handle.get_index();
+            if source.changes.len() <= index {
+                source.changes.resize_with(index + 1, Vec::new);
+            }
+
+            let name = scopes
+                .iter()
+                .cloned()
+                .chain([name.to_string()])
+                .collect::<Vec<_>>()
+                .join(".");
+            source
+                .ids
+                .insert(id_code(source.signals.len()), source.signals.len());
+            source.signals.push((name, index));
+        }
+        _ => (),
+    })?;
+
+    let limit = limit.map_or(usize::MAX, |limit| limit as usize);
+    reader.read_signals(&FstFilter::all(), |time, handle, value| {
+        let time = time as i64;
+        if source.timestamps.last().map(Timestamp::get_value) != Some(time) {
+            if source.timestamps.len() >= limit {
+                return;
+            }
+            source.timestamps.push(Timestamp::new(time));
+        }
+
+        let value = match value {
+            FstSignalValue::String(bits) => {
+                let bits: Vec<_> = bits.iter().map(|&bit| bit_value(bit)).collect();
+                let width = bits.len();
+
+                SignalValue::Literal(bits, width as _)
+            }
+            FstSignalValue::Real(value) => SignalValue::Symbol(value.to_string()),
+        };
+        if let Some(changes) = source.changes.get_mut(handle.get_index()) {
+            changes.push((time, value));
+        }
+    })?;
+
+    Ok((source, header))
+}
+
+impl WaveformSource for FstSource {
+    fn signal_ids(&self) -> Vec<String> {
+        (0..self.signals.len()).map(id_code).collect()
+    }
+
+    fn signal_name(&self, id: &str) -> Option<String> {
+        let (name, _) = &self.signals[*self.ids.get(id)?];
+
+        Some(name.clone())
+    }
+
+    fn timestamps(&self) -> Vec<Timestamp> {
+        self.timestamps.clone()
+    }
+
+    fn value(&self, id: &str, timestamp: Timestamp) -> Option<SignalValue> {
+        let (_, handle) = self.signals[*self.ids.get(id)?];
+        let changes = &self.changes[handle];
+        let index = changes.partition_point(|(time, _)| *time <= timestamp.get_value());
+        let (_, value) = changes.get(index.checked_sub(1)?)?;
+
+        Some(value.clone())
+    }
+}
+
+/// The value of a bit written as a VCD character.
+fn bit_value(bit: u8) -> BitValue {
+    match bit {
+        b'0' => BitValue::Low,
+        b'1' => BitValue::High,
+        b'z' | b'Z' => BitValue::HighZ,
+        _ => BitValue::Undefined,
+    }
+}
+
+/// Format an FST timescale exponent as a VCD timescale, e.g. `-10` is `100ps`.
+fn timescale(exponent: i8) -> String {
+    let units = [
+        (0, "s"),
+        (-3, "ms"),
+        (-6, "us"),
+        (-9, "ns"),
+        (-12, "ps"),
+        (-15, "fs"),
+    ];
+    let exponent = i32::from(exponent).clamp(-15, 2);
+    let (unit_exponent, unit) = units
+        .into_iter()
+        .find(|(unit_exponent, _)| exponent >= *unit_exponent)
+        .unwrap();
+
+    format!("{}{unit}", 10_u32.pow((exponent - unit_exponent) as u32))
+}
+
+/// Create a VCD identifier code for a signal index, using the printable ASCII characters.
+fn id_code(mut index: usize) -> String {
+    const FIRST: u8 = b'!';
+    const COUNT: usize = (b'~' - b'!' + 1) as usize;
+
+    let mut code = String::new();
+    loop {
+        code.push((FIRST + (index % COUNT) as u8) as char);
+        index /= COUNT;
+        if index == 0 {
+            break code;
+        }
+        index -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timescales() {
+        let timescales = [
+            (2, "100s"),
+            (0, "1s"),
+            (-1, "100ms"),
+            (-9, "1ns"),
+            (-10, "100ps"),
+            (-11, "10ps"),
+            (-15, "1fs"),
+            // Out of range exponents are clamped
+            (-20, "1fs"),
+            (5, "100s"),
+        ];
+
+        for (exponent, expected) in timescales {
+            assert_eq!(timescale(exponent), expected, "{exponent}");
+        }
+    }
+
+    #[test]
+    fn id_codes() {
+        let codes = [
+            (0, "!"),
+            (1, "\""),
+            (93, "~"),
+            // Two characters, with the first one least significant
+            (94, "!!"),
+            (95, "\"!"),
+            (94 + 94, "!\""),
+            (94 + 94 * 94, "!!!"),
+        ];
+
+        for (index, expected) in codes {
+            assert_eq!(id_code(index), expected, "{index}");
+        }
+    }
+
+    #[test]
+    fn detect_fst_header() {
+        let mut header = vec![FST_BL_HDR];
+        header.extend_from_slice(&HEADER_LENGTH.to_be_bytes());
+        header.extend_from_slice(&[0; 16]);
+        header.extend_from_slice(&ENDIAN_TEST.to_le_bytes());
+
+        assert!(is_fst(Path::new("dump"), &header));
+        assert!(is_fst(Path::new("dump.FST"), b"$date"));
+        assert!(!is_fst(Path::new("dump.vcd"), b"$date"));
+
+        // A NUL byte alone, or a header too short to check, is not enough
+        assert!(!is_fst(Path::new("dump"), &[0; 64]));
+        assert!(!is_fst(Path::new("dump"), &header[..header.len() - 1]));
+    }
+}
@@ -1,6 +1,9 @@
 //! Platform-neutral GPU state management and rendering.
 
+use log::{info, warn};
 use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 use winit::dpi::PhysicalSize;
 
@@ -18,15 +21,40 @@ pub enum Error {
     /// Equivalent to [`wgpu::CreateSurfaceError`]
     #[error("Unable to create a surface.")]
     CreateSurface(#[from] wgpu::CreateSurfaceError),
+    /// A headless GPU has no surface to present to
+    #[error("The GPU has no surface to render to.")]
+    Headless,
+}
+
+/// Errors that can occur while capturing a frame to an image file.
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    /// Equivalent to [`wgpu::BufferAsyncError`]
+    #[error("Unable to read the captured frame from the GPU.")]
+    Map(#[from] wgpu::BufferAsyncError),
+    /// The image could not be encoded or written
+    #[error("Unable to save image to `{}`", .0.display())]
+    Save(PathBuf, #[source] image::ImageError),
 }
 
 pub struct Gpu {
+    adapter: wgpu::Adapter,
+    adapters: Vec<wgpu::AdapterInfo>,
     pub(crate) device: wgpu::Device,
     pub(crate) queue: wgpu::Queue,
     pub(crate) texture_format: wgpu::TextureFormat,
-    surface: wgpu::Surface,
+    surface: Option<wgpu::Surface>,
     window_size: winit::dpi::PhysicalSize<u32>,
     alpha_mode: wgpu::CompositeAlphaMode,
+    alpha_modes: Vec<wgpu::CompositeAlphaMode>,
+    present_mode: wgpu::PresentMode,
+    present_modes: Vec<wgpu::PresentMode>,
+
+    // Multisample anti-aliasing: the sample count, those supported, and the multisampled target
+    // that is resolved to the surface.
+    sample_count: u32,
+    sample_counts: Vec<u32>,
+    msaa_view: Option<wgpu::TextureView>,
 }
 
 impl Gpu {
@@ -35,54 +63,199 @@ impl Gpu {
     /// # Safety
     ///
     /// The caller must ensure that the window reference outlives the returned `Gpu` instance.
+    ///
+    /// The primary backends are used if no adapter is found on the requested `backends`. The adapter
+    /// named `preferred_adapter` is used if it is available, otherwise one is chosen automatically.
+    /// Anti-aliasing is disabled if the adapter does not support `sample_count`, and the surface is
+    /// opaque if it cannot be `transparent`.
     pub unsafe fn new<W: HasRawDisplayHandle + HasRawWindowHandle>(
         window: &W,
         window_size: PhysicalSize<u32>,
+        backends: wgpu::Backends,
+        present_mode: wgpu::PresentMode,
+        preferred_adapter: Option<&str>,
+        sample_count: u32,
+        transparent: bool,
     ) -> Result<Self, Error> {
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::PRIMARY,
-            ..Default::default()
-        });
-        let surface = instance.create_surface(window)?;
-        let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions {
-            compatible_surface: Some(&surface),
-            force_fallback_adapter: false,
-            power_preference: wgpu::PowerPreference::HighPerformance,
-        });
-        let adapter = pollster::block_on(adapter).ok_or(Error::AdapterNotFound)?;
+        let mut found = Self::request_adapter(window, backends, preferred_adapter)?;
+        if found.is_none() && backends != wgpu::Backends::PRIMARY {
+            warn!("No adapter found for backends {backends:?}, retrying with primary backends");
+            found = Self::request_adapter(window, wgpu::Backends::PRIMARY, preferred_adapter)?;
+        }
+        let (surface, adapter, adapters) = found.ok_or(Error::AdapterNotFound)?;
+        info!("Using adapter: {:?}", adapter.get_info());
         let (device, queue) =
             pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None))?;
 
-        let texture_format = wgpu::TextureFormat::Bgra8UnormSrgb;
         let surface_capabilities = surface.get_capabilities(&adapter);
-        let alpha_mode = surface_capabilities.alpha_modes[0];
+        let texture_format = choose_texture_format(&surface_capabilities.formats);
+        info!("Using surface format: {texture_format:?}");
+        let alpha_modes = surface_capabilities.alpha_modes;
+        let alpha_mode = choose_alpha_mode(&alpha_modes, transparent);
+        let present_modes = surface_capabilities.present_modes;
+        let present_mode = validate_present_mode(present_mode, &present_modes);
+        let sample_counts = supported_sample_counts(&adapter, texture_format);
+        let sample_count = if sample_counts.contains(&sample_count) {
+            sample_count
+        } else {
+            warn!("Sample count {sample_count} is not supported, disabling anti-aliasing");
+            1
+        };
 
-        let gpu = Self {
+        let mut gpu = Self {
+            adapter,
+            adapters,
             device,
             queue,
             texture_format,
-            surface,
+            surface: Some(surface),
             window_size,
             alpha_mode,
+            alpha_modes,
+            present_mode,
+            present_modes,
+            sample_count,
+            sample_counts,
+            msaa_view: None,
         };
         gpu.reconfigure_surface();
 
         Ok(gpu)
     }
 
-    fn reconfigure_surface(&self) {
-        self.surface.configure(
+    /// Create a surface for the window and find an adapter that can present to it.
+    ///
+    /// Also returns information about all adapters that can present to the surface.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the window reference outlives the returned surface.
+    unsafe fn request_adapter<W: HasRawDisplayHandle + HasRawWindowHandle>(
+        window: &W,
+        backends: wgpu::Backends,
+        preferred: Option<&str>,
+    ) -> Result<Option<(wgpu::Surface, wgpu::Adapter, Vec<wgpu::AdapterInfo>)>, Error> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends,
+            ..Default::default()
+        });
+        let surface = instance.create_surface(window)?;
+
+        let adapters: Vec<_> = instance
+            .enumerate_adapters(backends)
+            .filter(|adapter| adapter.is_surface_supported(&surface))
+            .collect();
+        let infos = adapters.iter().map(wgpu::Adapter::get_info).collect();
+
+        let preferred = preferred.and_then(|name| {
+            let adapter = adapters
+                .into_iter()
+                .find(|adapter| adapter.get_info().name == name);
+            if adapter.is_none() {
+                warn!("Preferred adapter `{name}` not found, choosing one automatically");
+            }
+
+            adapter
+        });
+        let adapter = preferred.or_else(|| find_adapter(&instance, Some(&surface), backends));
+
+        Ok(adapter.map(|adapter| (surface, adapter, infos)))
+    }
+
+    /// Create a GPU manager without a window, for rendering offscreen.
+    ///
+    /// The primary backends are used if no adapter is found on the requested `backends`.
+    pub fn headless(backends: wgpu::Backends) -> Result<Self, Error> {
+        let request = |backends| {
+            let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+                backends,
+                ..Default::default()
+            });
+            find_adapter(&instance, None, backends)
+        };
+
+        let mut adapter = request(backends);
+        if adapter.is_none() && backends != wgpu::Backends::PRIMARY {
+            warn!("No adapter found for backends {backends:?}, retrying with primary backends");
+            adapter = request(wgpu::Backends::PRIMARY);
+        }
+        let adapter = adapter.ok_or(Error::AdapterNotFound)?;
+        info!("Using adapter: {:?}", adapter.get_info());
+        let (device, queue) =
+            pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None))?;
+
+        Ok(Self {
+            adapter,
+            adapters: vec![],
+            device,
+            queue,
+            texture_format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            surface: None,
+            window_size: PhysicalSize::new(0, 0),
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            alpha_modes: vec![],
+            present_mode: wgpu::PresentMode::Fifo,
+            present_modes: vec![],
+            sample_count: 1,
+            sample_counts: vec![1],
+            msaa_view: None,
+        })
+    }
+
+    /// Configure the surface, and create the multisampled target to match it.
+    fn reconfigure_surface(&mut self) {
+        let surface = match self.surface.as_ref() {
+            Some(surface) => surface,
+            None => return,
+        };
+        let width = self.window_size.width.min(self.max_texture_size());
+        let height = self.window_size.height.min(self.max_texture_size());
+        surface.configure(
             &self.device,
             &wgpu::SurfaceConfiguration {
                 usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
                 format: self.texture_format,
-                width: self.window_size.width,
-                height: self.window_size.height,
-                present_mode: wgpu::PresentMode::AutoNoVsync,
+                width,
+                height,
+                present_mode: self.present_mode,
                 alpha_mode: self.alpha_mode,
                 view_formats: vec![],
             },
-        )
+        );
+
+        self.msaa_view = (self.sample_count > 1).then(|| {
+            let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("msaa_texture"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: self.sample_count,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.texture_format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            });
+
+            texture.create_view(&wgpu::TextureViewDescriptor::default())
+        });
+    }
+
+    /// Information about the adapter chosen for rendering.
+    pub fn adapter_info(&self) -> wgpu::AdapterInfo {
+        self.adapter.get_info()
+    }
+
+    /// Information about all adapters that can render to the window, for choosing a preferred one.
+    pub fn adapters(&self) -> &[wgpu::AdapterInfo] {
+        &self.adapters
+    }
+
+    /// The largest texture dimension supported by the device, which also limits the surface size.
+    pub fn max_texture_size(&self) -> u32 {
+        self.device.limits().max_texture_dimension_2d
     }
 
     pub(crate) fn resize(&mut self, window_size: PhysicalSize<u32>) {
@@ -90,20 +263,90 @@ impl Gpu {
         self.reconfigure_surface();
     }
 
+    /// Replace the device and queue after the device has been lost, e.g. by a driver reset.
+    ///
+    /// All resources created on the old device must be recreated by the caller.
+    pub(crate) fn recreate_device(&mut self) -> Result<(), Error> {
+        let (device, queue) = pollster::block_on(
+            self.adapter
+                .request_device(&wgpu::DeviceDescriptor::default(), None),
+        )?;
+        self.device = device;
+        self.queue = queue;
+        self.reconfigure_surface();
+
+        Ok(())
+    }
+
+    /// Whether the surface is blended with the windows behind it.
+    pub fn is_transparent(&self) -> bool {
+        is_transparent(self.alpha_mode)
+    }
+
+    /// Whether the surface can be blended with the windows behind it.
+    pub fn supports_transparency(&self) -> bool {
+        self.alpha_modes.iter().any(|&mode| is_transparent(mode))
+    }
+
+    /// Number of samples per pixel for anti-aliasing; 1 when it is disabled.
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Sample counts supported by the adapter, in increasing order.
+    pub fn sample_counts(&self) -> &[u32] {
+        &self.sample_counts
+    }
+
+    /// The multisampled render target, which is resolved to the surface texture.
+    ///
+    /// This is `None` when anti-aliasing is disabled.
+    pub(crate) fn msaa_view(&self) -> Option<&wgpu::TextureView> {
+        self.msaa_view.as_ref()
+    }
+
+    /// Change the sample count, disabling anti-aliasing if the adapter does not support it.
+    ///
+    /// Returns `true` if the sample count changed, and the renderer must be recreated to match.
+    pub(crate) fn set_sample_count(&mut self, sample_count: u32) -> bool {
+        let sample_count = if self.sample_counts.contains(&sample_count) {
+            sample_count
+        } else {
+            1
+        };
+        if sample_count == self.sample_count {
+            return false;
+        }
+
+        self.sample_count = sample_count;
+        self.reconfigure_surface();
+
+        true
+    }
+
+    /// Change the present mode, falling back to `Fifo` if the surface does not support it.
+    pub(crate) fn set_present_mode(&mut self, present_mode: wgpu::PresentMode) {
+        let present_mode = validate_present_mode(present_mode, &self.present_modes);
+        if present_mode != self.present_mode {
+            self.present_mode = present_mode;
+            self.reconfigure_surface();
+        }
+    }
+
     pub(crate) fn prepare(
         &mut self,
     ) -> Result<(wgpu::CommandEncoder, wgpu::SurfaceTexture), Error> {
-        let frame = self
-            .surface
-            .get_current_texture()
-            .or_else(|err| match err {
-                wgpu::SurfaceError::Outdated => {
-                    // Recreate the swap chain to mitigate race condition on drawing surface resize.
-                    self.reconfigure_surface();
-                    self.surface.get_current_texture()
-                }
-                err => Err(err),
-            })?;
+        let surface = self.surface.as_ref().ok_or(Error::Headless)?;
+        let frame = match surface.get_current_texture() {
+            Err(wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost) => {
+                // Recreate the swap chain to mitigate race condition on drawing surface resize,
+                // or after the surface was lost (e.g. on resume from sleep).
+                self.reconfigure_surface();
+                let surface = self.surface.as_ref().ok_or(Error::Headless)?;
+                surface.get_current_texture()
+            }
+            result => result,
+        }?;
         let encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -113,3 +356,236 @@ impl Gpu {
         Ok((encoder, frame))
     }
 }
+
+/// An offscreen render target that can be read back and saved as an image.
+pub(crate) struct Capture {
+    texture: wgpu::Texture,
+    pub(crate) view: wgpu::TextureView,
+    buffer: wgpu::Buffer,
+    format: wgpu::TextureFormat,
+    size: wgpu::Extent3d,
+    padded_bytes_per_row: u32,
+}
+
+impl Capture {
+    /// Create a render target with the same format as the window surface.
+    pub(crate) fn new(gpu: &Gpu, [width, height]: [u32; 2]) -> Self {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = gpu.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("capture_texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: gpu.texture_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // Buffer rows must be aligned for texture copies
+        let padded_bytes_per_row =
+            wgpu::util::align_to(width * 4, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("capture_buffer"),
+            size: padded_bytes_per_row as u64 * height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            texture,
+            view,
+            buffer,
+            format: gpu.texture_format,
+            size,
+            padded_bytes_per_row,
+        }
+    }
+
+    /// Copy the rendered texture to the readback buffer.
+    pub(crate) fn copy(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.copy_texture_to_buffer(
+            self.texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &self.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(self.padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            self.size,
+        );
+    }
+
+    /// Read back the copied frame and save it as a PNG.
+    ///
+    /// Blocks until the GPU has finished all submitted work.
+    pub(crate) fn save(self, device: &wgpu::Device, path: &Path) -> Result<(), CaptureError> {
+        let slice = self.buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver.recv().unwrap_or(Err(wgpu::BufferAsyncError))?;
+
+        let width = self.size.width;
+        let height = self.size.height;
+        let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+        for row in slice
+            .get_mapped_range()
+            .chunks(self.padded_bytes_per_row as usize)
+        {
+            pixels.extend_from_slice(&row[..width as usize * 4]);
+        }
+        self.buffer.unmap();
+
+        // Convert BGRA to RGBA
+        if matches!(
+            self.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        image::save_buffer(path, &pixels, width, height, image::ColorType::Rgba8)
+            .map_err(|err| CaptureError::Save(path.into(), err))
+    }
+}
+
+/// Find an adapter, preferring a high-performance hardware adapter.
+///
+/// A fallback (software) adapter is used if there is none, e.g. llvmpipe or WARP on headless
+/// servers and VMs.
+fn find_adapter(
+    instance: &wgpu::Instance,
+    compatible_surface: Option<&wgpu::Surface>,
+    backends: wgpu::Backends,
+) -> Option<wgpu::Adapter> {
+    let attempts = [
+        (false, wgpu::PowerPreference::HighPerformance),
+        (true, wgpu::PowerPreference::LowPower),
+    ];
+    for (force_fallback_adapter, power_preference) in attempts {
+        let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions {
+            compatible_surface,
+            force_fallback_adapter,
+            power_preference,
+        });
+        if let Some(adapter) = pollster::block_on(adapter) {
+            return Some(adapter);
+        }
+
+        if !force_fallback_adapter {
+            warn!("No hardware adapter found for backends {backends:?}, trying fallback");
+        }
+    }
+
+    None
+}
+
+/// Choose a surface format, preferring 8-bit sRGB formats that egui and image capture handle.
+///
+/// Any other sRGB format is the next choice, since egui outputs linear colors. The first supported
+/// format is used as a last resort.
+fn choose_texture_format(supported: &[wgpu::TextureFormat]) -> wgpu::TextureFormat {
+    let preferred = [
+        wgpu::TextureFormat::Bgra8UnormSrgb,
+        wgpu::TextureFormat::Rgba8UnormSrgb,
+    ];
+    let format = preferred
+        .into_iter()
+        .find(|format| supported.contains(format))
+        .or_else(|| {
+            supported
+                .iter()
+                .copied()
+                .find(|format| format.describe().srgb)
+        });
+
+    format.unwrap_or_else(|| match supported.first() {
+        Some(&format) => {
+            warn!("The surface supports no sRGB formats, colors may be wrong with {format:?}");
+            format
+        }
+        None => {
+            warn!("The surface reports no formats, assuming Bgra8UnormSrgb");
+            wgpu::TextureFormat::Bgra8UnormSrgb
+        }
+    })
+}
+
+/// Get the sample counts that can be used to render to and resolve textures of the given format.
+fn supported_sample_counts(adapter: &wgpu::Adapter, format: wgpu::TextureFormat) -> Vec<u32> {
+    let features = adapter.get_texture_format_features(format);
+    let resolve = features
+        .flags
+        .contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE);
+
+    [1, 2, 4, 8]
+        .into_iter()
+        .filter(|&count| count == 1 || (resolve && features.flags.sample_count_supported(count)))
+        .collect()
+}
+
+/// Choose how the surface is composited with the windows behind it.
+///
+/// egui draws premultiplied colors, so that mode is preferred for a transparent surface. An opaque
+/// surface is preferred otherwise.
+fn choose_alpha_mode(
+    supported: &[wgpu::CompositeAlphaMode],
+    transparent: bool,
+) -> wgpu::CompositeAlphaMode {
+    use wgpu::CompositeAlphaMode::{Opaque, PostMultiplied, PreMultiplied};
+
+    let preferred: &[_] = if transparent {
+        &[PreMultiplied, PostMultiplied, Opaque]
+    } else {
+        &[Opaque]
+    };
+    let alpha_mode = preferred.iter().find(|mode| supported.contains(*mode));
+    if transparent && !alpha_mode.is_some_and(|&mode| is_transparent(mode)) {
+        warn!("The surface does not support transparency");
+    }
+
+    alpha_mode
+        .or(supported.first())
+        .copied()
+        .unwrap_or_else(|| {
+            warn!("The surface reports no alpha modes, letting wgpu choose one");
+            wgpu::CompositeAlphaMode::Auto
+        })
+}
+
+fn is_transparent(alpha_mode: wgpu::CompositeAlphaMode) -> bool {
+    matches!(
+        alpha_mode,
+        wgpu::CompositeAlphaMode::PreMultiplied | wgpu::CompositeAlphaMode::PostMultiplied
+    )
+}
+
+/// Check a present mode against those supported by the surface.
+///
+/// The `Auto*` modes are always supported, since `wgpu` chooses an available mode for them.
+/// `Fifo` is always supported by every surface.
+fn validate_present_mode(
+    present_mode: wgpu::PresentMode,
+    supported: &[wgpu::PresentMode],
+) -> wgpu::PresentMode {
+    match present_mode {
+        wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync => present_mode,
+        _ if supported.contains(&present_mode) => present_mode,
+        _ => {
+            warn!("Present mode {present_mode:?} is not supported, falling back to Fifo");
+            wgpu::PresentMode::Fifo
+        }
+    }
+}
@@ -1,190 +1,4262 @@
-use dwfv::signaldb::{BitValue, SignalDB, SignalValue};
-use egui::{Context, Painter, Pos2, Rect, Ui, Vec2};
-use rfd::AsyncFileDialog;
+use crate::config::{
+    Backend, ColorScheme, Config, Palette, PresentMode, Theme, DEFAULT_SCROLL_ROWS, LINE_WIDTHS,
+    ROW_HEIGHTS, SCROLL_ROWS,
+};
+use crate::framework::UserEvent;
+use crate::gpu::Gpu;
+use crate::index::Index;
+use crate::radix::{reverse_bits, Pattern, Radix};
+use crate::stream::{self, Connection, Update};
+use crate::time::{format_time, parse_time, parse_timescale, unit_label, Timescale, SCALES};
+use crate::watch::{file_stamp, FileStamp, Watcher};
+use crate::waveform::{Header, LoadError, LoadOptions, Waveform, WaveformSource};
+use dwfv::signaldb::{BitValue, SignalValue, Timestamp};
+use egui::{
+    Color32, Context, Key, KeyboardShortcut, Modifiers, Painter, Pos2, Rect, Sense, Ui, Vec2,
+};
+use error_iter::ErrorIter as _;
+use log::{error, warn};
+use rfd::{AsyncFileDialog, AsyncMessageDialog, MessageButtons, MessageDialog, MessageLevel};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{BufWriter, Write as _};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
 use std::thread::JoinHandle;
+use std::time::Duration;
+use thiserror::Error;
+use winit::dpi::{LogicalSize, PhysicalPosition};
+use winit::event_loop::EventLoopProxy;
 use winit::window::Window;
 
+/// Default horizontal zoom, in pixels per time unit.
+const DEFAULT_ZOOM: f32 = 5.0;
+
+/// Zoom limits, in pixels per time unit.
+const MIN_ZOOM: f32 = 1.0e-9;
+const MAX_ZOOM: f32 = 1000.0;
+
+/// Color and spacing of the hatching over time ranges in which values were not recorded.
+const DUMPOFF_COLOR: Color32 = Color32::DARK_GRAY;
+const HATCH_SPACING: f32 = 8.0;
+
+/// Row height of the "Compact" preset, in points.
+const COMPACT_ROW_HEIGHT: f32 = 12.0;
+
+/// Colors of the measurement markers.
+const MARKER_A_COLOR: Color32 = Color32::LIGHT_BLUE;
+const MARKER_B_COLOR: Color32 = Color32::LIGHT_RED;
+
+/// Points scrolled per mouse wheel step by `egui-winit`, which converts steps to points.
+const POINTS_PER_SCROLL_LINE: f32 = 50.0;
+
+/// Maximum distance from a click to an edge that the cursor snaps to, in pixels.
+const SNAP_DISTANCE: f32 = 8.0;
+
+/// Height of the overview strip below the timeline header, in points.
+const OVERVIEW_HEIGHT: f32 = 16.0;
+
+/// Color of the crosshair that follows the pointer over the waveforms.
+const CROSSHAIR_COLOR: Color32 = Color32::from_gray(128);
+
+/// Color of bookmark glyphs in the timeline header.
+const BOOKMARK_COLOR: Color32 = Color32::KHAKI;
+
+/// Path given on the command line to read a VCD from standard input.
+pub const STDIN_PATH: &str = "-";
+
+/// Appended to the name of a file to get the name of its saved view.
+const SESSION_EXTENSION: &str = ".edgescan.ron";
+
+/// File extensions that can be opened.
+const SUPPORTED_EXTENSIONS: [&str; 3] = ["vcd", "gz", "fst"];
+
+/// Keyboard shortcuts for menu actions.
+const OPEN_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::O);
+const CLOSE_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::W);
+const QUIT_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Q);
+const ABOUT_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::F1);
+const FIND_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::F);
+const GO_TO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::G);
+const COMMANDS_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::P);
+
+/// Number of times to retry an automatic reload that fails, e.g. while the file is being written.
+const MAX_RELOAD_RETRIES: u32 = 5;
+
+/// Minimum distance between timeline ticks, in pixels.
+const MIN_TICK_SPACING: f32 = 80.0;
+
+/// Indentation per scope level in the signal tree, in pixels.
+const SCOPE_INDENT: f32 = 12.0;
+
+/// Seconds between key presses after which type-to-search starts a new search.
+const TYPE_SEARCH_TIMEOUT: f64 = 1.0;
+
+/// Seconds that animated zoom and scroll transitions take.
+const VIEW_ANIMATION_TIME: f64 = 0.15;
+
+#[derive(Debug, Error)]
+enum ExportError {
+    #[error("Unable to write `{}`", .0.display())]
+    Write(PathBuf, #[source] std::io::Error),
+
+    #[error("Unable to save `{}`", .0.display())]
+    Serialize(PathBuf, #[source] ron::Error),
+}
+
+#[derive(Debug, Error)]
+enum SignalSetError {
+    #[error("Unable to read `{}`", .0.display())]
+    Read(PathBuf, #[source] std::io::Error),
+
+    #[error("`{}` is not a signal set", .0.display())]
+    Parse(PathBuf, #[source] ron::error::SpannedError),
+}
+
+/// Formats that the view can be exported to.
+#[derive(Clone, Copy, Debug)]
+enum Export {
+    Image,
+    Svg,
+    Csv,
+    SignalSet,
+}
+
+/// Actions that can be found by name in the command palette.
+///
+/// Each is also in a menu, or has a shortcut.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Command {
+    Open,
+    ConnectStream,
+    FileInfo,
+    ExportImage,
+    ExportSvg,
+    ExportCsv,
+    SaveSignalSet,
+    LoadSignalSet,
+    Reload,
+    Close,
+    Quit,
+    Preferences,
+    Theme(Theme),
+    FindValue,
+    GoToTime,
+    CursorValues,
+    ZoomToFit,
+    ZoomToSelection,
+    ResetView,
+    PreviousEdge,
+    NextEdge,
+    PreviousChange,
+    NextChange,
+    SetMarkerA,
+    SetMarkerB,
+    ClearMarkers,
+    AddBookmark,
+    About,
+}
+
+/// The outcome of loading a file on a background thread.
+///
+/// `None` means the user cancelled the file dialog, or declined to load a large file. A loaded file
+/// comes with the timestamp limit it was loaded with.
+type LoadResult = Option<Result<(PathBuf, Waveform, Option<FileStamp>, Option<u64>), LoadError>>;
+
+/// How much of a file to load.
+#[derive(Clone, Copy)]
+enum LoadLimit {
+    /// Ask before loading a large file, and offer to load only its first timestamps.
+    Ask,
+    /// Load at most this many timestamps, or the whole file.
+    Timestamps(Option<u64>),
+}
+
+/// A time range to fit to the width of the view.
+#[derive(Clone, Copy)]
+enum ZoomTarget {
+    /// All timestamps.
+    All,
+    /// The range between the measurement markers.
+    Markers,
+    /// An explicit range. Missing ends default to the first and last timestamps.
+    Range(Option<i64>, Option<i64>),
+}
+
+/// The connection to a simulation that streams the dump of a document.
+struct LiveStream {
+    address: String,
+
+    // Updates from the thread reading the stream, until the connection ends.
+    connection: Option<Connection>,
+
+    // Why the connection ended, if it was lost.
+    error: Option<String>,
+
+    // Scroll to the latest time as the dump grows.
+    follow: bool,
+}
+
+/// A transition of the view to a new zoom and time at its left edge.
+#[derive(Clone, Copy)]
+struct ViewAnimation {
+    from: (f32, f64),
+    to: (f32, f64),
+    /// egui time at which the transition started.
+    started: f64,
+}
+
+/// A request to move the cursor to the next or previous signal transition.
+#[derive(Clone, Copy)]
+struct EdgeSearch {
+    forward: bool,
+    /// Search for transitions on any signal instead of only the selected signal.
+    any_signal: bool,
+}
+
+/// Maps between time and horizontal screen coordinates.
+#[derive(Clone, Copy)]
+struct Timeline {
+    /// Screen X coordinate of `start`.
+    origin: f32,
+    /// First timestamp in the VCD.
+    start: i64,
+    /// Pixels per time unit.
+    zoom: f32,
+}
+
+/// A row in the waveform view.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+enum Displayed {
+    /// A signal, by ID.
+    Signal(String),
+    /// A labeled heading that separates groups of signals.
+    Divider(String),
+}
+
+/// Line width and colors for drawing a waveform.
+#[derive(Clone, Copy)]
+struct WaveformStyle {
+    width: f32,
+    /// Color of the signal.
+    color: Color32,
+    /// Colors of high-impedance (Z) and unknown (X) values.
+    highz: Color32,
+    unknown: Color32,
+}
+
+/// Display settings for a single signal.
+#[derive(Debug, Default)]
+struct SignalSettings {
+    /// Shown instead of the full name.
+    alias: Option<String>,
+    radix: Radix,
+    /// Show multi-bit values with their bits in the opposite order.
+    reverse_bits: bool,
+    color: Option<Color32>,
+    plot: Plot,
+    /// Values at the bottom and top of the row in analog plots, or `None` to fit the visible
+    /// values.
+    plot_range: Option<(f64, f64)>,
+}
+
+/// How the values of a signal are drawn.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+enum Plot {
+    /// Levels for single bits, and labeled segments for buses.
+    #[default]
+    Digital,
+    /// The numeric value as the height of a stepped line.
+    Step,
+    /// The numeric value as the height of a line interpolated between changes.
+    Line,
+    /// Only the formatted values, separated by ticks at transitions.
+    Text,
+}
+
+/// Display settings of a signal in a saved view.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct SavedSignal {
+    alias: Option<String>,
+    radix: Radix,
+    reverse_bits: bool,
+    /// RGBA color.
+    color: Option<[u8; 4]>,
+    plot: Plot,
+    plot_range: Option<(f64, f64)>,
+}
+
+/// Displayed rows and their settings, saved by signal name to load into any file with the same
+/// signals.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct SignalSet {
+    rows: Vec<SignalSetRow>,
+}
+
+/// A row in a signal set: a signal by full name, with its settings if it has its own.
+#[derive(Debug, Deserialize, Serialize)]
+enum SignalSetRow {
+    Signal(String, Option<SavedSignal>),
+    Divider(String),
+}
+
+/// The view of a file, saved next to it to restore when the file is opened again.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+struct ViewState {
+    zoom: f32,
+    /// Time at the left edge of the waveforms.
+    view_time: f64,
+    displayed: Vec<Displayed>,
+    /// Settings of each signal that has its own.
+    signal_settings: HashMap<String, SavedSignal>,
+    collapsed_scopes: HashSet<String>,
+    /// Scope that the signal list is restricted to.
+    scope_filter: Option<String>,
+    selected: Option<String>,
+    cursor: Option<i64>,
+    marker_a: Option<i64>,
+    marker_b: Option<i64>,
+    bookmarks: Vec<(String, i64)>,
+}
+
+/// A row in the signal list: either a scope header or a signal.
+#[derive(Debug)]
+enum Row {
+    Scope {
+        /// Full dotted path of the scope, e.g. `top.cpu`.
+        path: String,
+        label: String,
+        depth: usize,
+    },
+    Signal {
+        id: String,
+        label: String,
+        depth: usize,
+    },
+}
+
+/// An open file and its view state, shown in a tab.
+struct Document {
+    vcd: Box<dyn WaveformSource>,
+
+    // Timestamps and value changes of `vcd`, cached for drawing.
+    index: Index,
+
+    // Metadata declared in the VCD header.
+    header: Header,
+
+    // Unit of the times, or `None` to show them as a count of ticks. It is read from the header,
+    // unless the header has no valid timescale and the user chose a unit instead.
+    timescale: Option<Timescale>,
+    timescale_valid: bool,
+
+    // Time ranges between `$dumpoff` and `$dumpon`.
+    dumpoff: Vec<Range<i64>>,
+
+    // Path of the file, and its stamp when it was loaded.
+    path: PathBuf,
+    file_stamp: Option<FileStamp>,
+    file_changed: bool,
+
+    // Number of timestamps the file was limited to when it was loaded.
+    timestamp_limit: Option<u64>,
+
+    // The stream the dump is received from, if it was connected to instead of opened.
+    live: Option<LiveStream>,
+
+    // Horizontal zoom in pixels per time unit.
+    zoom: f32,
+    zoom_to: Option<ZoomTarget>,
+
+    // Horizontal scroll offset to apply on the next frame.
+    scroll_offset: Option<f32>,
+
+    // Time at the left edge of the view in the last frame, and the time to scroll there next.
+    // Used to link the views in split view.
+    view_time: f64,
+    scroll_time: Option<f64>,
+
+    // Transition to the last zoom or jump target, and whether they are animated at all.
+    animation: Option<ViewAnimation>,
+    animate: bool,
+
+    // Per-signal display settings, keyed by signal ID.
+    signal_settings: HashMap<String, SignalSettings>,
+
+    // Time of the vertical cursor.
+    cursor: Option<i64>,
+
+    // Times of the measurement markers.
+    marker_a: Option<i64>,
+    marker_b: Option<i64>,
+
+    // Signal name filter.
+    filter: String,
+    filter_case_sensitive: bool,
+
+    // Only list the signals below this scope, e.g. `top.cpu`, or all signals if `None`.
+    scope_filter: Option<String>,
+
+    // Signals and dividers shown in the waveform view, in display order.
+    displayed: Vec<Displayed>,
+
+    // Index of the displayed row being dragged to a new position.
+    dragging: Option<usize>,
+
+    // Full paths of scopes collapsed in the signal tree.
+    collapsed_scopes: HashSet<String>,
+
+    // Signal ID selected for edge navigation.
+    selected: Option<String>,
+    edge_search: Option<EdgeSearch>,
+
+    // Named times, sorted by time, and the name for the next bookmark.
+    bookmarks: Vec<(String, i64)>,
+    bookmark_name: String,
+
+    // Time to center in the view on the next frame.
+    center_on: Option<i64>,
+
+    // The value segment that the waveform context menu was opened on, as `(from, to)`.
+    segment_menu: Option<(i64, i64)>,
+
+    // Dim the names of signals that don't change in the visible time range.
+    highlight_active: bool,
+
+    // Show the overview of the whole time range below the timeline header.
+    show_overview: bool,
+
+    // Snap the cursor to edges of the clicked signal.
+    snap_to_edges: bool,
+
+    // Radix of signals without their own settings, and rows per mouse wheel step, from the
+    // preferences.
+    default_radix: Radix,
+    scroll_rows: u32,
+
+    // Colors and line width of waveforms, from the preferences.
+    palette: Palette,
+    line_width: f32,
+
+    // Show the values at the cursor in their own column, from the preferences.
+    value_column: bool,
+
+    // Value to find on the selected signal, and the result of the last search.
+    find_text: String,
+    find_status: Option<String>,
+
+    // Time to move the cursor to, and the error from the last attempt.
+    go_to_text: String,
+    go_to_status: Option<String>,
+
+    // Value segments and samples of the row being drawn, kept to reuse their allocations.
+    segments: Vec<(i64, i64, SignalValue)>,
+    samples: Vec<(Rect, SignalValue)>,
+
+    // Row with the keyboard focus in the waveform view, and the text typed to search for a row
+    // with the time of the last key press.
+    focused_row: Option<usize>,
+    type_search: (String, f64),
+}
+
+/// Settings edited in the Preferences window, written to the config when applied.
+#[derive(Debug)]
+struct Preferences {
+    theme: Theme,
+    color_scheme: ColorScheme,
+    line_width: f32,
+    transparent_background: bool,
+    row_height: Option<f32>,
+    default_radix: Radix,
+    scroll_rows: u32,
+    auto_reload: bool,
+    single_instance: bool,
+    large_file_size: u64,
+    large_file_timestamps: u64,
+    present_mode: PresentMode,
+    sample_count: u32,
+    backend: Backend,
+    adapter: Option<String>,
+}
+
+/// A single file drawn without the rest of the UI, for rendering to an image.
+pub(crate) struct Snapshot(Document);
+
 pub struct Gui {
     enabled: bool,
     about_open: bool,
-    vcd: Option<SignalDB>,
-    file_dialog: Option<JoinHandle<Option<SignalDB>>>,
+    find_open: bool,
+    go_to_open: bool,
+    info_open: bool,
+    values_open: bool,
+    frame_times_open: bool,
+    quit: bool,
+
+    // Command palette, with the text to find commands by and the highlighted match.
+    commands_open: bool,
+    command_query: String,
+    command_index: usize,
+
+    adapter_info: wgpu::AdapterInfo,
+    adapters: Vec<wgpu::AdapterInfo>,
+    sample_counts: Vec<u32>,
+    transparency_supported: bool,
+    file_dialog: Option<JoinHandle<LoadResult>>,
+    file_hovered: bool,
+
+    // "Connect to Stream" window, with the address typed and the connection waiting for a header.
+    connect_open: bool,
+    connect_address: String,
+    connecting: Option<(String, Connection)>,
+
+    // Open files, and the index of the active tab.
+    documents: Vec<Document>,
+    active: usize,
+
+    // Tab shown below the active tab in split view, and whether their zoom and scroll are linked.
+    compare: Option<usize>,
+    link_views: bool,
+
+    // Save dialog for exports, and the paths to capture the next frame to as an image or SVG.
+    export_dialog: Option<(Export, JoinHandle<Option<PathBuf>>)>,
+    signal_set_dialog: Option<JoinHandle<Option<PathBuf>>>,
+    capture: Option<PathBuf>,
+    svg_capture: Option<PathBuf>,
+
+    // Area of the main content, which SVG exports are cropped to.
+    content_rect: Rect,
+
+    // Settings being edited in the Preferences window, while it is open.
+    preferences: Option<Preferences>,
+
+    // Watching open files for changes, the files last given to the watcher, and the index of the
+    // document being reloaded.
+    watcher: Watcher,
+    watched: Vec<(PathBuf, Option<FileStamp>)>,
+    reloading: Option<usize>,
+    reload_retries: u32,
+
+    // Files sent by other instances, opened one at a time.
+    pending_files: VecDeque<PathBuf>,
+
+    // Bytes read and total bytes for the file being loaded.
+    load_progress: Option<Receiver<(u64, u64)>>,
+    progress: Option<(u64, u64)>,
+
+    // Size in MiB above which loading asks first, and the timestamps a large file can be limited to.
+    large_file: (u64, u64),
+}
+
+impl Gui {
+    /// Create the GUI, loading `path` in the background if one is given.
+    pub(crate) fn new(path: Option<PathBuf>, gpu: &Gpu, proxy: EventLoopProxy<UserEvent>) -> Self {
+        let mut gui = Self {
+            enabled: true,
+            about_open: false,
+            find_open: false,
+            go_to_open: false,
+            info_open: false,
+            values_open: false,
+            frame_times_open: false,
+            commands_open: false,
+            command_query: String::new(),
+            command_index: 0,
+            quit: false,
+            adapter_info: gpu.adapter_info(),
+            adapters: gpu.adapters().to_vec(),
+            sample_counts: gpu.sample_counts().to_vec(),
+            transparency_supported: gpu.supports_transparency(),
+            file_dialog: None,
+            file_hovered: false,
+            connect_open: false,
+            connect_address: String::new(),
+            connecting: None,
+            documents: Vec::new(),
+            active: 0,
+            compare: None,
+            link_views: true,
+            export_dialog: None,
+            signal_set_dialog: None,
+            capture: None,
+            svg_capture: None,
+            content_rect: Rect::NOTHING,
+            preferences: None,
+            watcher: Watcher::new(proxy),
+            watched: Vec::new(),
+            reloading: None,
+            reload_retries: 0,
+            pending_files: VecDeque::new(),
+            load_progress: None,
+            progress: None,
+            large_file: (0, 0),
+        };
+        if let Some(path) = path {
+            gui.load(move || Some(path), LoadLimit::Ask);
+        }
+
+        gui
+    }
+
+    /// Create the UI using egui.
+    pub(crate) fn ui(&mut self, ctx: &Context, window: &Window, config: &mut Config) {
+        self.large_file = (config.large_file_size(), config.large_file_timestamps());
+
+        // Poll the file dialog
+        if let Some(receiver) = self.load_progress.as_ref() {
+            if let Some(progress) = receiver.try_iter().last() {
+                self.progress = Some(progress);
+            }
+        }
+        if let Some(handle) = self.file_dialog.as_ref() {
+            // Keep polling while the file is loading
+            ctx.request_repaint_after(Duration::from_millis(50));
+
+            if handle.is_finished() {
+                self.load_progress = None;
+                self.progress = None;
+
+                match self.file_dialog.take().unwrap().join() {
+                    Ok(Some(Ok((path, vcd, stamp, limit)))) => {
+                        if path != Path::new(STDIN_PATH) {
+                            config.add_recent_file(&path);
+                        }
+
+                        // A file that is already open is reloaded in its tab
+                        let index = self.documents.iter().position(|doc| doc.path == path);
+                        match index {
+                            Some(index) => {
+                                let doc = &mut self.documents[index];
+                                doc.replace(vcd, stamp);
+                                doc.timestamp_limit = limit;
+                                self.active = index;
+                            }
+                            None => {
+                                let mut doc = Document::new(path, vcd, stamp);
+                                doc.timestamp_limit = limit;
+                                doc.restore_session();
+                                self.documents.push(doc);
+                                self.active = self.documents.len() - 1;
+                            }
+                        }
+                        self.reload_retries = 0;
+                    }
+                    Ok(Some(Err(err))) => {
+                        let doc = self
+                            .reloading
+                            .and_then(|index| self.documents.get_mut(index));
+                        match doc {
+                            // The file may still be being written; try again on the next poll
+                            Some(doc) if self.reload_retries < MAX_RELOAD_RETRIES => {
+                                warn!("Reload failed, retrying: {err}");
+                                self.reload_retries += 1;
+                                doc.file_stamp = None;
+                            }
+                            doc => {
+                                // Don't retry until the file changes again
+                                if let Some(doc) = doc {
+                                    doc.file_stamp = file_stamp(&doc.path);
+                                }
+                                self.reload_retries = 0;
+                                show_error(err);
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+                self.enabled = true;
+                self.reloading = None;
+            }
+        }
+
+        // Poll the export dialog
+        if let Some((_, handle)) = self.export_dialog.as_ref() {
+            ctx.request_repaint_after(Duration::from_millis(50));
+
+            if handle.is_finished() {
+                let (export, handle) = self.export_dialog.take().unwrap();
+                match (export, handle.join().ok().flatten()) {
+                    (Export::Image, path) => self.capture = path,
+                    (Export::Svg, path) => self.svg_capture = path,
+                    (Export::Csv, Some(path)) => {
+                        let doc = self.documents.get(self.active);
+                        if let Some(Err(err)) = doc.map(|doc| doc.export_csv(&path)) {
+                            show_error(err);
+                        }
+                    }
+                    (Export::SignalSet, Some(path)) => {
+                        let doc = self.documents.get(self.active);
+                        if let Some(Err(err)) = doc.map(|doc| doc.save_signal_set(&path)) {
+                            show_error(err);
+                        }
+                    }
+                    (Export::Csv | Export::SignalSet, None) => (),
+                }
+                self.enabled = true;
+            }
+        }
+
+        // Poll the signal set dialog
+        if let Some(handle) = self.signal_set_dialog.as_ref() {
+            ctx.request_repaint_after(Duration::from_millis(50));
+
+            if handle.is_finished() {
+                let path = self.signal_set_dialog.take().unwrap().join().ok().flatten();
+                let doc = self.documents.get_mut(self.active);
+                if let Some((path, doc)) = path.zip(doc) {
+                    match doc.load_signal_set(&path) {
+                        Ok(missing) if !missing.is_empty() => show_missing_signals(&missing),
+                        Ok(_) => (),
+                        Err(err) => show_error(err),
+                    }
+                }
+                self.enabled = true;
+            }
+        }
+
+        // Poll the stream being connected to, and open it in a tab once its header has arrived
+        if let Some((_, connection)) = self.connecting.as_ref() {
+            ctx.request_repaint_after(stream::UPDATE_INTERVAL);
+
+            match connection.try_recv() {
+                Ok(Update::Waveform(waveform)) => {
+                    let (address, connection) = self.connecting.take().unwrap();
+                    let path = PathBuf::from(format!("tcp://{address}"));
+                    let mut doc = Document::new(path, waveform, None);
+                    doc.live = Some(LiveStream {
+                        address,
+                        connection: Some(connection),
+                        error: None,
+                        follow: true,
+                    });
+                    self.documents.push(doc);
+                    self.active = self.documents.len() - 1;
+                    self.connect_open = false;
+                }
+                Ok(Update::Closed(err)) => {
+                    self.connecting = None;
+                    if let Some(err) = err {
+                        show_error(err);
+                    }
+                }
+                Err(TryRecvError::Empty) => (),
+                Err(TryRecvError::Disconnected) => self.connecting = None,
+            }
+        }
+
+        // Take the dumps received from live streams
+        for doc in &mut self.documents {
+            if doc.poll_stream() {
+                ctx.request_repaint_after(stream::UPDATE_INTERVAL);
+            }
+        }
+
+        // Watch the open files for changes, with the stamps they were loaded with
+        let watched = self
+            .documents
+            .iter()
+            .filter(|doc| doc.reloadable())
+            .map(|doc| (doc.path.as_path(), doc.file_stamp));
+        let last = self
+            .watched
+            .iter()
+            .map(|(path, stamp)| (path.as_path(), *stamp));
+        if !watched.clone().eq(last) {
+            self.watched = watched
+                .map(|(path, stamp)| (path.to_path_buf(), stamp))
+                .collect();
+            self.watcher.watch(self.watched.clone());
+        }
+
+        // Changes wait for the file being loaded
+        if self.file_dialog.is_none() {
+            let mut changed = None;
+            for path in self.watcher.changed() {
+                for (index, doc) in self.documents.iter_mut().enumerate() {
+                    if doc.path != path {
+                        continue;
+                    }
+                    if !config.auto_reload() {
+                        doc.file_changed = true;
+                    } else if changed.is_none() {
+                        changed = Some(index);
+                    } else {
+                        // Only one file can be loaded at a time; forgetting the stamp of the rest
+                        // has them reported again once it is watched without it
+                        doc.file_stamp = None;
+                    }
+                }
+            }
+            if let Some(index) = changed {
+                self.reload(index);
+            }
+        }
+
+        // Open files sent by other instances
+        if self.enabled && self.file_dialog.is_none() {
+            // The running instance was asked to open the file, so its extension isn't checked
+            if let Some(path) = self.pending_files.pop_front() {
+                self.load(move || Some(path), LoadLimit::Ask);
+            }
+        }
+
+        // Handle keyboard shortcuts, unless a dialog is open
+        if self.enabled {
+            let (open, close, quit, about, find, go_to, commands) = ctx.input_mut(|i| {
+                (
+                    i.consume_shortcut(&OPEN_SHORTCUT),
+                    i.consume_shortcut(&CLOSE_SHORTCUT),
+                    i.consume_shortcut(&QUIT_SHORTCUT),
+                    i.consume_shortcut(&ABOUT_SHORTCUT),
+                    i.consume_shortcut(&FIND_SHORTCUT),
+                    i.consume_shortcut(&GO_TO_SHORTCUT),
+                    i.consume_shortcut(&COMMANDS_SHORTCUT),
+                )
+            });
+            if open {
+                self.open(window, config);
+            }
+            if close {
+                self.close(self.active);
+            }
+            self.quit |= quit;
+            self.about_open |= about;
+            self.find_open |= find;
+            self.go_to_open |= go_to;
+            if commands {
+                self.open_commands();
+            }
+
+            if !ctx.wants_keyboard_input() && ctx.input(|i| i.key_pressed(Key::F5)) {
+                self.reload(self.active);
+            }
+        }
+
+        // Draw the menu bar
+        egui::TopBottomPanel::top("menubar_container").show(ctx, |ui| {
+            ui.set_enabled(self.enabled);
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    let button = egui::Button::new("Open")
+                        .shortcut_text(ctx.format_shortcut(&OPEN_SHORTCUT));
+                    if ui.add(button).clicked() {
+                        self.open(window, config);
+                        ui.close_menu();
+                    }
+
+                    if ui.button("Connect to Stream...").clicked() {
+                        self.connect_open = true;
+                        ui.close_menu();
+                    }
+
+                    ui.menu_button("Recent Files", |ui| {
+                        ui.set_enabled(!config.recent_files().is_empty());
+                        for path in config.recent_files() {
+                            let path = PathBuf::from(path);
+                            let button = egui::Button::new(path.display().to_string());
+                            if ui
+                                .add_enabled(path.exists(), button)
+                                .on_disabled_hover_text("File not found")
+                                .clicked()
+                            {
+                                self.load(move || Some(path), LoadLimit::Ask);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+
+                    let button = egui::Button::new("File Info...");
+                    if ui.add_enabled(!self.documents.is_empty(), button).clicked() {
+                        self.info_open = true;
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+                    if ui.button("Export Image...").clicked() {
+                        self.export(Export::Image, window);
+                        ui.close_menu();
+                    }
+                    if ui.button("Export SVG...").clicked() {
+                        self.export(Export::Svg, window);
+                        ui.close_menu();
+                    }
+                    let button = egui::Button::new("Export CSV...");
+                    let displayed = self.documents.get(self.active).map(|doc| &doc.displayed);
+                    let enabled = displayed.is_some_and(|displayed| !displayed.is_empty());
+                    if ui.add_enabled(enabled, button).clicked() {
+                        self.export(Export::Csv, window);
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+                    let button = egui::Button::new("Save Signal Set...");
+                    if ui.add_enabled(enabled, button).clicked() {
+                        self.export(Export::SignalSet, window);
+                        ui.close_menu();
+                    }
+                    let button = egui::Button::new("Load Signal Set...");
+                    if ui.add_enabled(!self.documents.is_empty(), button).clicked() {
+                        self.pick_signal_set(window);
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+                    let button = egui::Button::new("Reload").shortcut_text("F5");
+                    let reloadable = self
+                        .documents
+                        .get(self.active)
+                        .is_some_and(Document::reloadable);
+                    if ui.add_enabled(reloadable, button).clicked() {
+                        self.reload(self.active);
+                        ui.close_menu();
+                    }
+                    let mut auto_reload = config.auto_reload();
+                    if ui
+                        .checkbox(&mut auto_reload, "Reload Automatically")
+                        .changed()
+                    {
+                        config.set_auto_reload(auto_reload);
+                    }
+
+                    ui.separator();
+                    let button = egui::Button::new("Close")
+                        .shortcut_text(ctx.format_shortcut(&CLOSE_SHORTCUT));
+                    if ui.add_enabled(!self.documents.is_empty(), button).clicked() {
+                        self.close(self.active);
+                        ui.close_menu();
+                    }
+                    let button = egui::Button::new("Quit")
+                        .shortcut_text(ctx.format_shortcut(&QUIT_SHORTCUT));
+                    if ui.add(button).clicked() {
+                        self.quit = true;
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("Edit", |ui| {
+                    if ui.button("Preferences...").clicked() {
+                        self.preferences = Some(Preferences::new(config));
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+                    if ui
+                        .button("Reload Settings")
+                        .on_hover_text("Read the config file again, after editing it by hand")
+                        .clicked()
+                    {
+                        self.update_config(ctx, window, config, Config::reload);
+                        ui.close_menu();
+                    }
+                    if ui.button("Reset Settings...").clicked() {
+                        ui.close_menu();
+
+                        let confirmed = MessageDialog::new()
+                            .set_title("Reset Settings")
+                            .set_description("Reset all settings to their defaults?")
+                            .set_level(MessageLevel::Warning)
+                            .set_buttons(MessageButtons::YesNo)
+                            .show();
+                        if confirmed {
+                            self.update_config(ctx, window, config, Config::reset);
+                        }
+                    }
+                });
+                ui.menu_button("View", |ui| {
+                    let button = egui::Button::new("Command Palette...")
+                        .shortcut_text(ctx.format_shortcut(&COMMANDS_SHORTCUT));
+                    if ui.add(button).clicked() {
+                        self.open_commands();
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+                    ui.menu_button("Theme", |ui| {
+                        let mut theme = config.theme();
+                        for value in Theme::ALL {
+                            if ui
+                                .radio_value(&mut theme, value, value.to_string())
+                                .clicked()
+                            {
+                                config.set_theme(theme);
+                                ctx.set_visuals(theme.visuals(window.theme()));
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    ui.menu_button("Row Height", |ui| {
+                        let mut row_height = config.row_height();
+                        let presets = [("Automatic", None), ("Compact", Some(COMPACT_ROW_HEIGHT))];
+                        for (label, value) in presets {
+                            if ui.radio_value(&mut row_height, value, label).clicked() {
+                                config.set_row_height(row_height);
+                                ui.close_menu();
+                            }
+                        }
+
+                        let mut height = row_height.unwrap_or(COMPACT_ROW_HEIGHT);
+                        let slider = egui::Slider::new(&mut height, ROW_HEIGHTS).suffix(" pt");
+                        if ui.add(slider).changed() {
+                            config.set_row_height(Some(height));
+                        }
+                    });
+                    let mut value_column = config.value_column();
+                    if ui
+                        .checkbox(&mut value_column, "Value Column")
+                        .on_hover_text("Show the values at the cursor next to the signal names")
+                        .changed()
+                    {
+                        config.set_value_column(value_column);
+                    }
+                    let mut animate_view = config.animate_view();
+                    if ui
+                        .checkbox(&mut animate_view, "Animate Zooming")
+                        .on_hover_text("Move the view gradually when zooming to fit or jumping")
+                        .changed()
+                    {
+                        config.set_animate_view(animate_view);
+                    }
+
+                    ui.separator();
+                    let button = egui::Button::new("Find Value...")
+                        .shortcut_text(ctx.format_shortcut(&FIND_SHORTCUT));
+                    if ui.add(button).clicked() {
+                        self.find_open = true;
+                        ui.close_menu();
+                    }
+                    let button = egui::Button::new("Go to Time...")
+                        .shortcut_text(ctx.format_shortcut(&GO_TO_SHORTCUT));
+                    if ui.add(button).clicked() {
+                        self.go_to_open = true;
+                        ui.close_menu();
+                    }
+                    ui.checkbox(&mut self.values_open, "Cursor Values")
+                        .on_hover_text("List the values of the displayed signals at the cursor");
+                    ui.add_enabled_ui(self.documents.len() > 1, |ui| {
+                        ui.menu_button("Compare With", |ui| {
+                            if ui.radio_value(&mut self.compare, None, "None").clicked() {
+                                ui.close_menu();
+                            }
+                            for (index, doc) in self.documents.iter().enumerate() {
+                                if index == self.active {
+                                    continue;
+                                }
+                                if ui
+                                    .radio_value(&mut self.compare, Some(index), doc.title())
+                                    .clicked()
+                                {
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    });
+                    ui.add_enabled(
+                        self.compare.is_some(),
+                        egui::Checkbox::new(&mut self.link_views, "Link Zoom and Scroll"),
+                    );
+
+                    if let Some(doc) = self.documents.get_mut(self.active) {
+                        ui.separator();
+                        doc.view_menu(ui);
+                    }
+                });
+                ui.menu_button("Debug", |ui| {
+                    ui.checkbox(&mut self.frame_times_open, "Frame Times")
+                        .on_hover_text("Show how long each frame takes to prepare and render");
+                });
+                ui.menu_button("Help", |ui| {
+                    let button = egui::Button::new("About...")
+                        .shortcut_text(ctx.format_shortcut(&ABOUT_SHORTCUT));
+                    if ui.add(button).clicked() {
+                        self.about_open = true;
+                        ui.close_menu();
+                    }
+                });
+            });
+        });
+
+        // Draw the tab bar
+        if !self.documents.is_empty() {
+            egui::TopBottomPanel::top("tabs").show(ctx, |ui| {
+                ui.set_enabled(self.enabled);
+                ui.horizontal_wrapped(|ui| {
+                    let mut close = None;
+                    for (index, doc) in self.documents.iter().enumerate() {
+                        ui.selectable_value(&mut self.active, index, doc.title())
+                            .on_hover_text(doc.path.display().to_string());
+                        if ui.small_button("×").on_hover_text("Close").clicked() {
+                            close = Some(index);
+                        }
+                        ui.separator();
+                    }
+                    if let Some(index) = close {
+                        self.close(index);
+                    }
+                });
+            });
+        }
+
+        // Draw the status bar
+        if self.progress.is_none() {
+            let mut reload = false;
+            egui::TopBottomPanel::bottom("statusbar").show(ctx, |ui| {
+                match self.documents.get_mut(self.active) {
+                    Some(doc) => reload = doc.draw_status_bar(ui),
+                    None => {
+                        ui.label("No file loaded");
+                    }
+                }
+            });
+            if reload {
+                self.reload(self.active);
+            }
+        }
+
+        // Draw the signal list
+        let doc = self.documents.get_mut(self.active);
+        if let Some(doc) = doc.filter(|_| self.progress.is_none()) {
+            egui::SidePanel::left("signal_list")
+                .resizable(true)
+                .show(ctx, |ui| {
+                    ui.set_enabled(self.enabled);
+                    doc.draw_signal_list(ui);
+                });
+        }
+
+        // Draw the main content area, with waveform colors that suit the theme
+        let dark_mode = ctx.style().visuals.dark_mode;
+        for doc in &mut self.documents {
+            doc.default_radix = config.default_radix();
+            doc.scroll_rows = config.scroll_rows();
+            doc.palette = config.color_scheme().palette(dark_mode);
+            doc.line_width = config.line_width();
+            doc.value_column = config.value_column();
+            doc.animate = config.animate_view();
+        }
+        egui::CentralPanel::default().show(ctx, |ui| {
+            self.content_rect = ui.max_rect();
+            if let Some((read, total)) = self.progress {
+                draw_progress(ui, read, total);
+                return;
+            }
+
+            ui.set_enabled(self.enabled);
+            let compare = self
+                .compare
+                .filter(|&index| index != self.active && index < self.documents.len());
+            let row_height = config.row_height();
+            if let Some(compare) = compare {
+                self.draw_split(ui, compare, row_height);
+            } else if let Some(doc) = self.documents.get_mut(self.active) {
+                doc.draw_vcd(ui, true, row_height);
+            }
+
+            if self.file_hovered {
+                let painter = ui.painter();
+                painter.rect_filled(ui.max_rect(), 0.0, Color32::from_black_alpha(192));
+                painter.text(
+                    ui.max_rect().center(),
+                    egui::Align2::CENTER_CENTER,
+                    "Drop to open",
+                    egui::TextStyle::Heading.resolve(ui.style()),
+                    Color32::WHITE,
+                );
+            }
+        });
+
+        // Draw the windows (if requested by the user)
+        self.about_window(ctx);
+        self.find_window(ctx);
+        self.go_to_window(ctx);
+        self.connect_window(ctx);
+        self.info_window(ctx);
+        self.values_window(ctx);
+        self.preferences_window(ctx, window, config);
+        self.command_window(ctx, window, config);
+    }
+
+    /// Show the command palette with an empty search.
+    fn open_commands(&mut self) {
+        self.commands_open = true;
+        self.command_query.clear();
+        self.command_index = 0;
+    }
+
+    /// Show the command palette, and run the command chosen from it.
+    ///
+    /// Commands are found by the words typed, in any order. The arrow keys move through the
+    /// matches, Enter runs the highlighted one, and Escape closes the palette.
+    fn command_window(&mut self, ctx: &Context, window: &Window, config: &mut Config) {
+        if !self.commands_open || !self.enabled {
+            return;
+        }
+
+        let query = self.command_query.to_lowercase();
+        let commands: Vec<_> = Command::all()
+            .filter(|command| self.command_enabled(*command))
+            .filter(|command| {
+                let label = command.to_string().to_lowercase();
+                query.split_whitespace().all(|word| label.contains(word))
+            })
+            .collect();
+
+        let (up, down, enter, escape) = ctx.input(|i| {
+            (
+                i.key_pressed(Key::ArrowUp),
+                i.key_pressed(Key::ArrowDown),
+                i.key_pressed(Key::Enter),
+                i.key_pressed(Key::Escape),
+            )
+        });
+        if up {
+            self.command_index = self.command_index.saturating_sub(1);
+        }
+        if down {
+            self.command_index += 1;
+        }
+        self.command_index = self.command_index.min(commands.len().saturating_sub(1));
+
+        let mut chosen = enter
+            .then(|| commands.get(self.command_index).copied())
+            .flatten();
+        egui::Window::new("Command Palette")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, Vec2::new(0.0, 48.0))
+            .show(ctx, |ui| {
+                let edit = egui::TextEdit::singleline(&mut self.command_query)
+                    .hint_text("Type a command")
+                    .desired_width(320.0);
+                let response = ui.add(edit);
+                response.request_focus();
+                if response.changed() {
+                    self.command_index = 0;
+                }
+
+                ui.separator();
+                if commands.is_empty() {
+                    ui.weak("No matching commands");
+                }
+                egui::ScrollArea::vertical()
+                    .max_height(320.0)
+                    .show(ui, |ui| {
+                        let layout = egui::Layout::top_down_justified(egui::Align::LEFT);
+                        ui.with_layout(layout, |ui| {
+                            for (index, command) in commands.iter().enumerate() {
+                                let selected = index == self.command_index;
+                                let mut button =
+                                    egui::Button::new(command.to_string()).frame(selected);
+                                if let Some(shortcut) = command.shortcut(ctx) {
+                                    button = button.shortcut_text(shortcut);
+                                }
+                                let response = ui.add(button);
+                                if selected && (up || down) {
+                                    response.scroll_to_me(None);
+                                }
+                                if response.clicked() {
+                                    chosen = Some(*command);
+                                }
+                            }
+                        });
+                    });
+            });
+
+        if escape || chosen.is_some() {
+            self.commands_open = false;
+        }
+        if let Some(command) = chosen {
+            self.run_command(command, ctx, window, config);
+        }
+    }
+
+    /// Check whether a command can run now, the same as its menu item.
+    fn command_enabled(&self, command: Command) -> bool {
+        let doc = self.documents.get(self.active);
+        let cursor = doc.is_some_and(|doc| doc.cursor.is_some());
+
+        match command {
+            Command::FileInfo
+            | Command::LoadSignalSet
+            | Command::Close
+            | Command::ZoomToFit
+            | Command::ResetView
+            | Command::PreviousEdge
+            | Command::NextEdge
+            | Command::PreviousChange
+            | Command::NextChange
+            | Command::ClearMarkers => doc.is_some(),
+            Command::ExportCsv | Command::SaveSignalSet => {
+                doc.is_some_and(|doc| !doc.displayed.is_empty())
+            }
+            Command::Reload => doc.is_some_and(Document::reloadable),
+            Command::ZoomToSelection => {
+                doc.is_some_and(|doc| doc.marker_a.is_some() && doc.marker_b.is_some())
+            }
+            Command::SetMarkerA | Command::SetMarkerB | Command::AddBookmark => cursor,
+            _ => true,
+        }
+    }
+
+    /// Run a command chosen from the command palette.
+    fn run_command(
+        &mut self,
+        command: Command,
+        ctx: &Context,
+        window: &Window,
+        config: &mut Config,
+    ) {
+        match command {
+            Command::Open => self.open(window, config),
+            Command::ConnectStream => self.connect_open = true,
+            Command::FileInfo => self.info_open = true,
+            Command::ExportImage => self.export(Export::Image, window),
+            Command::ExportSvg => self.export(Export::Svg, window),
+            Command::ExportCsv => self.export(Export::Csv, window),
+            Command::SaveSignalSet => self.export(Export::SignalSet, window),
+            Command::LoadSignalSet => self.pick_signal_set(window),
+            Command::Reload => self.reload(self.active),
+            Command::Close => self.close(self.active),
+            Command::Quit => self.quit = true,
+            Command::Preferences => self.preferences = Some(Preferences::new(config)),
+            Command::Theme(theme) => {
+                config.set_theme(theme);
+                ctx.set_visuals(theme.visuals(window.theme()));
+            }
+            Command::FindValue => self.find_open = true,
+            Command::GoToTime => self.go_to_open = true,
+            Command::CursorValues => self.values_open = !self.values_open,
+            Command::About => self.about_open = true,
+            _ => {
+                if let Some(doc) = self.documents.get_mut(self.active) {
+                    doc.run_command(command);
+                }
+            }
+        }
+    }
+
+    /// Reload or reset the config, and apply the settings that are not read on every frame.
+    fn update_config(
+        &mut self,
+        ctx: &Context,
+        window: &Window,
+        config: &mut Config,
+        update: fn(&mut Config) -> Result<(), crate::config::Error>,
+    ) {
+        if let Err(err) = update(config) {
+            show_error(err);
+            return;
+        }
+
+        ctx.set_visuals(config.theme().visuals(window.theme()));
+        let (width, height) = config.get_window_size();
+        window.set_inner_size(LogicalSize::new(width, height));
+        if let Some((x, y)) = config.get_window_position() {
+            window.set_outer_position(PhysicalPosition::new(x, y));
+        }
+
+        // Show the new settings if the Preferences window is open
+        if self.preferences.is_some() {
+            self.preferences = Some(Preferences::new(config));
+        }
+    }
+
+    /// Save the view of every open file, to restore when it is opened again.
+    pub(crate) fn save_sessions(&self) {
+        for doc in &self.documents {
+            doc.save_session();
+        }
+    }
+
+    /// Whether the frame time overlay is shown.
+    pub(crate) fn frame_times_visible(&self) -> bool {
+        self.frame_times_open
+    }
+
+    /// Whether the user asked to quit the application.
+    pub(crate) fn quit_requested(&self) -> bool {
+        self.quit
+    }
+
+    /// Ask for a waveform file to open.
+    fn open(&mut self, window: &Window, config: &Config) {
+        let mut dialog = AsyncFileDialog::new().set_parent(window);
+        if let Some(dir) = config.last_directory() {
+            dialog = dialog.set_directory(dir);
+        }
+        let dialog = dialog
+            .add_filter("Waveforms", &SUPPORTED_EXTENSIONS)
+            .add_filter("Value Change Dump", &["vcd", "gz"])
+            .add_filter("Fast Signal Trace", &["fst"]);
+
+        self.load(
+            move || {
+                pollster::block_on(dialog.pick_file()).map(|handle| handle.path().to_path_buf())
+            },
+            LoadLimit::Ask,
+        );
+    }
+
+    /// Close the tab at `index`.
+    fn close(&mut self, index: usize) {
+        if index >= self.documents.len() {
+            return;
+        }
+
+        self.documents.remove(index).save_session();
+        if self.active > index || self.active >= self.documents.len() {
+            self.active = self.active.saturating_sub(1);
+        }
+        self.compare = match self.compare {
+            Some(compare) if compare > index => Some(compare - 1),
+            Some(compare) if compare == index => None,
+            compare => compare,
+        };
+    }
+
+    /// Draw the active tab above the compared tab.
+    ///
+    /// The views share a cursor. The view under the pointer leads; the other follows its zoom and
+    /// scroll position when the views are linked.
+    fn draw_split(&mut self, ui: &mut Ui, compare: usize, row_height: Option<f32>) {
+        let height = (ui.available_height() - ui.spacing().item_spacing.y) / 2.0;
+        let size = Vec2::new(ui.available_width(), height);
+
+        let mut leader = self.active;
+        for index in [self.active, compare] {
+            let focused = index == self.active;
+            let doc = &mut self.documents[index];
+            let response = ui.allocate_ui(size, |ui| {
+                ui.push_id(index, |ui| {
+                    ui.strong(doc.title());
+                    doc.draw_vcd(ui, focused, row_height);
+                });
+            });
+            if ui.rect_contains_pointer(response.response.rect) {
+                leader = index;
+            }
+        }
+
+        let follower = if leader == self.active {
+            compare
+        } else {
+            self.active
+        };
+        let lead = &self.documents[leader];
+        let (cursor, zoom, view_time) = (lead.cursor, lead.zoom, lead.view_time);
+        let doc = &mut self.documents[follower];
+
+        let mut changed = doc.cursor != cursor;
+        doc.cursor = cursor;
+
+        // Allow half a pixel of error, so the views don't keep nudging each other
+        if self.link_views
+            && (doc.zoom != zoom || (doc.view_time - view_time).abs() * zoom as f64 > 0.5)
+        {
+            doc.zoom = zoom;
+            doc.scroll_time = Some(view_time);
+            changed = true;
+        }
+        if changed {
+            ui.ctx().request_repaint();
+        }
+    }
+
+    /// Load a VCD file on a background thread.
+    ///
+    /// `pick` chooses the path to load, and may block (e.g. on a file dialog). The UI is disabled
+    /// until loading completes.
+    fn load<F>(&mut self, pick: F, limit: LoadLimit)
+    where
+        F: FnOnce() -> Option<PathBuf> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let (size, timestamps) = self.large_file;
+
+        self.file_dialog = Some(std::thread::spawn(move || {
+            let path = pick()?;
+            let limit = match limit {
+                LoadLimit::Ask => confirm_large_file(&path, size, timestamps)?,
+                LoadLimit::Timestamps(limit) => limit,
+            };
+
+            // Stamp the file before reading, so changes made while reading are detected
+            let stamp = file_stamp(&path);
+            let options = LoadOptions {
+                limit,
+                progress: Some(sender),
+            };
+            let vcd = if path == Path::new(STDIN_PATH) {
+                // Standard input has no size to show progress against
+                Waveform::from_reader(&path, std::io::stdin().lock())
+            } else {
+                Waveform::load_path_with(&path, options)
+            };
+
+            Some(vcd.map(|vcd| (path, vcd, stamp, limit)))
+        }));
+        self.load_progress = Some(receiver);
+        self.enabled = false;
+    }
+
+    /// Ask where to save an export on a background thread.
+    ///
+    /// The UI is disabled until the dialog is closed.
+    fn export(&mut self, export: Export, window: &Window) {
+        let (file_name, filter, extension) = match export {
+            Export::Image => ("edgescan.png", "PNG Image", "png"),
+            Export::Svg => ("edgescan.svg", "SVG Image", "svg"),
+            Export::Csv => ("edgescan.csv", "Comma-Separated Values", "csv"),
+            Export::SignalSet => ("signals.ron", "Signal Set", "ron"),
+        };
+        let dialog = AsyncFileDialog::new()
+            .set_parent(window)
+            .set_file_name(file_name)
+            .add_filter(filter, &[extension]);
+        let handle = std::thread::spawn(move || {
+            pollster::block_on(dialog.save_file()).map(|handle| handle.path().to_path_buf())
+        });
+        self.export_dialog = Some((export, handle));
+        self.enabled = false;
+    }
+
+    /// Ask for a signal set to load on a background thread.
+    ///
+    /// The UI is disabled until the dialog is closed.
+    fn pick_signal_set(&mut self, window: &Window) {
+        let dialog = AsyncFileDialog::new()
+            .set_parent(window)
+            .add_filter("Signal Set", &["ron"]);
+        self.signal_set_dialog = Some(std::thread::spawn(move || {
+            pollster::block_on(dialog.pick_file()).map(|handle| handle.path().to_path_buf())
+        }));
+        self.enabled = false;
+    }
+
+    /// Take the path that the next rendered frame should be saved to, if an export was requested.
+    pub(crate) fn take_capture(&mut self) -> Option<PathBuf> {
+        self.capture.take()
+    }
+
+    /// Take the path that this frame should be saved to as an SVG, with the area to crop it to.
+    pub(crate) fn take_svg_capture(&mut self) -> Option<(PathBuf, Rect)> {
+        self.svg_capture
+            .take()
+            .map(|path| (path, self.content_rect))
+    }
+
+    /// Reload the file in the tab at `index` from disk, keeping its view.
+    fn reload(&mut self, index: usize) {
+        if self.file_dialog.is_some() {
+            return;
+        }
+        if let Some(doc) = self.documents.get(index).filter(|doc| doc.reloadable()) {
+            // A file that was limited when it was opened stays limited
+            let path = doc.path.clone();
+            let limit = LoadLimit::Timestamps(doc.timestamp_limit);
+            self.load(move || Some(path), limit);
+            self.reloading = Some(index);
+        }
+    }
+
+    /// Show or hide the "drop to open" overlay while a file is dragged over the window.
+    pub(crate) fn set_file_hovered(&mut self, hovered: bool) {
+        self.file_hovered = hovered;
+    }
+
+    /// Open a file sent by another instance once the current file, if any, has loaded.
+    pub(crate) fn open_file(&mut self, path: PathBuf) {
+        self.pending_files.push_back(path);
+    }
+
+    /// Open a file that was dropped on the window.
+    pub(crate) fn drop_file(&mut self, path: PathBuf) {
+        self.file_hovered = false;
+
+        // Ignore drops while another file is loading
+        if !self.enabled {
+            return;
+        }
+
+        let supported = path.extension().is_some_and(|ext| {
+            SUPPORTED_EXTENSIONS
+                .iter()
+                .any(|supported| ext.eq_ignore_ascii_case(supported))
+        });
+        if supported {
+            self.load(move || Some(path), LoadLimit::Ask);
+        } else {
+            show_error(LoadError::Unsupported(path));
+        }
+    }
+
+    /// Show "Find Value" window, searching the selected signal in the active tab.
+    fn find_window(&mut self, ctx: &Context) {
+        let doc = self.documents.get_mut(self.active);
+
+        egui::Window::new("Find Value")
+            .open(&mut self.find_open)
+            .enabled(self.enabled)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let doc = match doc {
+                    Some(doc) => doc,
+                    None => {
+                        ui.label("No file loaded");
+                        return;
+                    }
+                };
+                let signal = doc.selected.as_ref().map_or_else(
+                    || "None (click a signal name)".to_string(),
+                    |id| signal_name(&doc.vcd, id),
+                );
+                ui.label(format!("Signal: {signal}"));
+
+                let radix = doc.radix(doc.selected.as_deref());
+                let edit = egui::TextEdit::singleline(&mut doc.find_text)
+                    .hint_text(format!("{radix}, e.g. 0x3?"));
+                let response = ui.add(edit);
+                let enter = response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+
+                ui.horizontal(|ui| {
+                    if ui.button("Find Previous").clicked() {
+                        doc.find_value(false);
+                    }
+                    if ui.button("Find Next").clicked() || enter {
+                        doc.find_value(true);
+                    }
+                });
+                if let Some(status) = &doc.find_status {
+                    ui.label(status.as_str());
+                }
+            });
+    }
+
+    /// Show "Go to Time" window, moving the cursor in the active tab.
+    fn go_to_window(&mut self, ctx: &Context) {
+        let doc = self.documents.get_mut(self.active);
+
+        egui::Window::new("Go to Time")
+            .open(&mut self.go_to_open)
+            .enabled(self.enabled)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let doc = match doc {
+                    Some(doc) => doc,
+                    None => {
+                        ui.label("No file loaded");
+                        return;
+                    }
+                };
+
+                let edit =
+                    egui::TextEdit::singleline(&mut doc.go_to_text).hint_text("e.g. 1.5us or 3000");
+                let response = ui.add(edit);
+                let enter = response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                if ui.button("Go").clicked() || enter {
+                    doc.go_to_status = doc.go_to_time().err();
+                }
+                if let Some(status) = &doc.go_to_status {
+                    ui.label(status.as_str());
+                }
+            });
+    }
+
+    /// Show "Connect to Stream" window, asking for the address of a simulation streaming a VCD.
+    ///
+    /// The window stays open until the VCD header has arrived, and the stream is opened in a tab.
+    fn connect_window(&mut self, ctx: &Context) {
+        egui::Window::new("Connect to Stream")
+            .open(&mut self.connect_open)
+            .enabled(self.enabled)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Read a VCD from a simulation over TCP, or from a named pipe.");
+                let edit = egui::TextEdit::singleline(&mut self.connect_address)
+                    .hint_text("host:port or pipe path");
+                let response = ui.add(edit);
+                let enter = response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+
+                let address = self.connect_address.trim();
+                match &self.connecting {
+                    None => {
+                        let button = egui::Button::new("Connect");
+                        if ui.add_enabled(!address.is_empty(), button).clicked()
+                            || (enter && !address.is_empty())
+                        {
+                            let connection = stream::connect(address.to_string());
+                            self.connecting = Some((address.to_string(), connection));
+                        }
+                    }
+                    Some((address, _)) => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label(format!("Waiting for `{address}`..."));
+                        });
+                        if ui.button("Cancel").clicked() {
+                            self.connecting = None;
+                        }
+                    }
+                }
+            });
+    }
+
+    /// Show "File Info" window, describing the file in the active tab.
+    fn info_window(&mut self, ctx: &Context) {
+        let doc = self.documents.get(self.active);
+
+        egui::Window::new("File Info")
+            .open(&mut self.info_open)
+            .enabled(self.enabled)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let doc = match doc {
+                    Some(doc) => doc,
+                    None => {
+                        ui.label("No file loaded");
+                        return;
+                    }
+                };
+                let timestamps = doc.index.timestamps();
+                let range = timestamps
+                    .first()
+                    .zip(timestamps.last())
+                    .map(|(first, last)| {
+                        format!(
+                            "{} - {}",
+                            time_label(first.get_value(), doc.timescale),
+                            time_label(last.get_value(), doc.timescale),
+                        )
+                    });
+                let header = &doc.header;
+                let rows = [
+                    ("Path", Some(doc.path.display().to_string())),
+                    ("Date", header.date.clone()),
+                    ("Version", header.version.clone()),
+                    ("Timescale", header.timescale.clone()),
+                    ("Signals", Some(doc.vcd.signal_ids().len().to_string())),
+                    ("Time range", range),
+                ];
+
+                egui::Grid::new("file_info").num_columns(2).show(ui, |ui| {
+                    for (label, value) in rows {
+                        ui.label(format!("{label}:"));
+                        match value {
+                            Some(value) => ui.label(value),
+                            None => ui.weak("Not declared"),
+                        };
+                        ui.end_row();
+                    }
+                });
+                if let Some(limit) = doc.timestamp_limit {
+                    ui.weak(format!("Only the first {limit} timestamps were loaded"));
+                }
+            });
+    }
+
+    /// Show "Cursor Values" window, listing the displayed signals in the active tab with their
+    /// values at the cursor.
+    fn values_window(&mut self, ctx: &Context) {
+        let doc = self.documents.get(self.active);
+
+        egui::Window::new("Cursor Values")
+            .open(&mut self.values_open)
+            .enabled(self.enabled)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                let values = match doc.map(Document::cursor_values) {
+                    Some(Some(values)) => values,
+                    Some(None) => {
+                        ui.label("Click a waveform to place the cursor");
+                        return;
+                    }
+                    None => {
+                        ui.label("No file loaded");
+                        return;
+                    }
+                };
+
+                if ui.button("Copy").clicked() {
+                    let text = values
+                        .iter()
+                        .map(|(name, value)| format!("{name} = {value}\n"))
+                        .collect();
+                    ui.output_mut(|output| output.copied_text = text);
+                }
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("cursor_values")
+                        .num_columns(2)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for (name, value) in &values {
+                                ui.label(name);
+                                ui.monospace(value);
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+    }
+
+    /// Show "Preferences" window.
+    ///
+    /// Changes are written to the config when applied, or when the window is closed.
+    fn preferences_window(&mut self, ctx: &Context, window: &Window, config: &mut Config) {
+        let preferences = match self.preferences.as_mut() {
+            Some(preferences) => preferences,
+            None => return,
+        };
+
+        let mut open = true;
+        let mut close = false;
+        egui::Window::new("Preferences")
+            .open(&mut open)
+            .enabled(self.enabled)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.heading("Appearance");
+                egui::Grid::new("appearance").num_columns(2).show(ui, |ui| {
+                    ui.label("Theme:");
+                    egui::ComboBox::from_id_source("theme")
+                        .selected_text(preferences.theme.to_string())
+                        .show_ui(ui, |ui| {
+                            for value in Theme::ALL {
+                                ui.selectable_value(
+                                    &mut preferences.theme,
+                                    value,
+                                    value.to_string(),
+                                );
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Waveform colors:");
+                    egui::ComboBox::from_id_source("color_scheme")
+                        .selected_text(preferences.color_scheme.to_string())
+                        .show_ui(ui, |ui| {
+                            for value in ColorScheme::ALL {
+                                let label = value.to_string();
+                                ui.selectable_value(&mut preferences.color_scheme, value, label);
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Line width:");
+                    let slider = egui::Slider::new(&mut preferences.line_width, LINE_WIDTHS);
+                    ui.add(slider.suffix(" pt"));
+                    ui.end_row();
+
+                    ui.label("Row height:");
+                    ui.horizontal(|ui| {
+                        let mut automatic = preferences.row_height.is_none();
+                        if ui.checkbox(&mut automatic, "Automatic").changed() {
+                            preferences.row_height = (!automatic).then_some(COMPACT_ROW_HEIGHT);
+                        }
+                        if let Some(height) = preferences.row_height.as_mut() {
+                            ui.add(egui::Slider::new(height, ROW_HEIGHTS).suffix(" pt"));
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Background:");
+                    let checkbox =
+                        egui::Checkbox::new(&mut preferences.transparent_background, "Transparent");
+                    ui.add_enabled(self.transparency_supported, checkbox)
+                        .on_hover_text(
+                            "Show the windows behind EdgeScan where nothing is drawn. \
+                            Takes effect after restarting EdgeScan.",
+                        )
+                        .on_disabled_hover_text("Not supported by the GPU or window system");
+                    ui.end_row();
+                });
+
+                ui.separator();
+                ui.heading("Waveforms");
+                egui::Grid::new("waveforms").num_columns(2).show(ui, |ui| {
+                    ui.label("Default radix:");
+                    egui::ComboBox::from_id_source("radix")
+                        .selected_text(preferences.default_radix.to_string())
+                        .show_ui(ui, |ui| {
+                            for value in Radix::ALL {
+                                let label = value.to_string();
+                                ui.selectable_value(&mut preferences.default_radix, value, label);
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Scroll step:");
+                    let drag = egui::DragValue::new(&mut preferences.scroll_rows)
+                        .clamp_range(SCROLL_ROWS)
+                        .suffix(" rows");
+                    ui.add(drag).on_hover_text(
+                        "Rows scrolled per mouse wheel step. Hold Shift to scroll through time.",
+                    );
+                    ui.end_row();
+
+                    ui.label("Files:");
+                    ui.checkbox(&mut preferences.auto_reload, "Reload automatically");
+                    ui.end_row();
+
+                    ui.label("");
+                    ui.checkbox(&mut preferences.single_instance, "Open in this window")
+                        .on_hover_text(
+                            "Open files in this window when EdgeScan is started again. \
+                            Takes effect after restarting EdgeScan.",
+                        );
+                    ui.end_row();
+
+                    ui.label("Large files:");
+                    let drag = egui::DragValue::new(&mut preferences.large_file_size)
+                        .speed(16)
+                        .suffix(" MiB");
+                    ui.add(drag)
+                        .on_hover_text("Ask before loading files larger than this. 0 never asks.");
+                    ui.end_row();
+
+                    ui.label("");
+                    let drag = egui::DragValue::new(&mut preferences.large_file_timestamps)
+                        .speed(1000)
+                        .suffix(" timestamps");
+                    ui.add(drag).on_hover_text(
+                        "Offer to load only this many timestamps of a large file. \
+                        0 always loads the whole file.",
+                    );
+                    ui.end_row();
+                });
+
+                ui.separator();
+                ui.heading("Graphics");
+                egui::Grid::new("graphics").num_columns(2).show(ui, |ui| {
+                    ui.label("Present mode:");
+                    egui::ComboBox::from_id_source("present_mode")
+                        .selected_text(preferences.present_mode.to_string())
+                        .show_ui(ui, |ui| {
+                            for value in PresentMode::ALL {
+                                let label = value.to_string();
+                                ui.selectable_value(&mut preferences.present_mode, value, label);
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Anti-aliasing:");
+                    egui::ComboBox::from_id_source("sample_count")
+                        .selected_text(sample_count_label(preferences.sample_count))
+                        .show_ui(ui, |ui| {
+                            for &value in &self.sample_counts {
+                                let label = sample_count_label(value);
+                                ui.selectable_value(&mut preferences.sample_count, value, label);
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Backend:");
+                    egui::ComboBox::from_id_source("backend")
+                        .selected_text(preferences.backend.to_string())
+                        .show_ui(ui, |ui| {
+                            for value in Backend::ALL {
+                                let label = value.to_string();
+                                ui.selectable_value(&mut preferences.backend, value, label);
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Adapter:");
+                    let selected = preferences.adapter.as_deref().unwrap_or("Automatic");
+                    egui::ComboBox::from_id_source("adapter")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut preferences.adapter, None, "Automatic");
+                            for info in &self.adapters {
+                                let label = format!("{} ({:?})", info.name, info.backend);
+                                let value = Some(info.name.clone());
+                                ui.selectable_value(&mut preferences.adapter, value, label);
+                            }
+                        });
+                    ui.end_row();
+                });
+                ui.label(format!("In use: {}", self.adapter_info.name));
+                ui.label("Backend and adapter changes take effect after restarting EdgeScan.");
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Apply").clicked() {
+                        preferences.apply(ctx, window, config);
+                    }
+                    close = ui.button("Close").clicked();
+                });
+            });
+
+        if !open || close {
+            preferences.apply(ctx, window, config);
+            self.preferences = None;
+        }
+    }
+
+    /// Show "About" window.
+    fn about_window(&mut self, ctx: &Context) {
+        egui::Window::new("About EdgeScan")
+            .open(&mut self.about_open)
+            .enabled(self.enabled)
+            .collapsible(false)
+            .default_pos((175.0, 175.0))
+            .fixed_size((350.0, 100.0))
+            .show(ctx, |ui| {
+                ui.add_space(5.0);
+                ui.label(concat!("EdgeScan version ", env!("CARGO_PKG_VERSION")));
+                ui.add_space(10.0);
+                ui.label(env!("CARGO_PKG_DESCRIPTION"));
+                ui.label(concat!("By: ", env!("CARGO_PKG_AUTHORS")));
+                ui.add_space(10.0);
+                ui.label("Made with 💖 in San Francisco!");
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    ui.label("Website:");
+                    ui.hyperlink(env!("CARGO_PKG_HOMEPAGE"));
+                });
+                ui.add_space(10.0);
+
+                // Helps with reports of rendering issues
+                let info = &self.adapter_info;
+                let mut renderer = format!(
+                    "Renderer: {} ({:?}, {:?})",
+                    info.name, info.backend, info.device_type,
+                );
+                if !info.driver.is_empty() {
+                    renderer += &format!("\nDriver: {} {}", info.driver, info.driver_info);
+                }
+                ui.label(renderer);
+            });
+    }
+}
+
+impl Preferences {
+    fn new(config: &Config) -> Self {
+        Self {
+            theme: config.theme(),
+            color_scheme: config.color_scheme(),
+            line_width: config.line_width(),
+            transparent_background: config.transparent_background(),
+            row_height: config.row_height(),
+            default_radix: config.default_radix(),
+            scroll_rows: config.scroll_rows(),
+            auto_reload: config.auto_reload(),
+            single_instance: config.single_instance(),
+            large_file_size: config.large_file_size(),
+            large_file_timestamps: config.large_file_timestamps(),
+            present_mode: config.present_mode(),
+            sample_count: config.sample_count(),
+            backend: config.backend(),
+            adapter: config.adapter().map(str::to_string),
+        }
+    }
+
+    fn apply(&self, ctx: &Context, window: &Window, config: &mut Config) {
+        config.set_theme(self.theme);
+        ctx.set_visuals(self.theme.visuals(window.theme()));
+        config.set_color_scheme(self.color_scheme);
+        config.set_line_width(self.line_width);
+        config.set_transparent_background(self.transparent_background);
+        config.set_row_height(self.row_height);
+        config.set_default_radix(self.default_radix);
+        config.set_scroll_rows(self.scroll_rows);
+        config.set_auto_reload(self.auto_reload);
+        config.set_single_instance(self.single_instance);
+        config.set_large_file_size(self.large_file_size);
+        config.set_large_file_timestamps(self.large_file_timestamps);
+        config.set_present_mode(self.present_mode);
+        config.set_sample_count(self.sample_count);
+        config.set_backend(self.backend);
+        config.set_adapter(self.adapter.clone());
+    }
+}
+
+impl From<&SignalSettings> for SavedSignal {
+    fn from(settings: &SignalSettings) -> Self {
+        Self {
+            alias: settings.alias.clone(),
+            radix: settings.radix,
+            reverse_bits: settings.reverse_bits,
+            color: settings.color.map(|color| color.to_array()),
+            plot: settings.plot,
+            plot_range: settings.plot_range,
+        }
+    }
+}
+
+impl From<SavedSignal> for SignalSettings {
+    fn from(saved: SavedSignal) -> Self {
+        Self {
+            alias: saved.alias,
+            radix: saved.radix,
+            reverse_bits: saved.reverse_bits,
+            color: saved
+                .color
+                .map(|[r, g, b, a]| Color32::from_rgba_premultiplied(r, g, b, a)),
+            plot: saved.plot,
+            plot_range: saved.plot_range,
+        }
+    }
+}
+
+impl Default for ViewState {
+    fn default() -> Self {
+        Self {
+            zoom: DEFAULT_ZOOM,
+            view_time: 0.0,
+            displayed: Vec::new(),
+            signal_settings: HashMap::new(),
+            collapsed_scopes: HashSet::new(),
+            scope_filter: None,
+            selected: None,
+            cursor: None,
+            marker_a: None,
+            marker_b: None,
+            bookmarks: Vec::new(),
+        }
+    }
+}
+
+impl Snapshot {
+    /// Display all signals, zoomed to fit the time range.
+    ///
+    /// Missing ends of the range default to the first and last timestamps.
+    pub(crate) fn new(path: PathBuf, vcd: Waveform, from: Option<i64>, to: Option<i64>) -> Self {
+        let mut doc = Document::new(path, vcd, None);
+        doc.displayed = doc
+            .vcd
+            .signal_ids()
+            .into_iter()
+            .map(Displayed::Signal)
+            .collect();
+        doc.zoom_to = Some(ZoomTarget::Range(from, to));
+        doc.show_overview = false;
+
+        Self(doc)
+    }
+
+    /// Draw the waveforms across the whole screen.
+    pub(crate) fn ui(&mut self, ctx: &Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            self.0.draw_vcd(ui, false, None);
+        });
+    }
+}
+
+impl Document {
+    fn new(path: PathBuf, waveform: Waveform, file_stamp: Option<FileStamp>) -> Self {
+        let (vcd, header, dumpoff) = waveform.into_parts();
+        let timescale = header.timescale.as_deref().and_then(parse_timescale);
+        if timescale.is_none() {
+            warn!("`{}` has no valid timescale", path.display());
+        }
+
+        Self {
+            index: Index::new(&vcd),
+            vcd,
+            header,
+            timescale,
+            timescale_valid: timescale.is_some(),
+            dumpoff,
+            path,
+            file_stamp,
+            file_changed: false,
+            timestamp_limit: None,
+            live: None,
+            zoom: DEFAULT_ZOOM,
+            zoom_to: None,
+            scroll_offset: None,
+            view_time: 0.0,
+            scroll_time: None,
+            animation: None,
+            animate: false,
+            signal_settings: HashMap::new(),
+            cursor: None,
+            marker_a: None,
+            marker_b: None,
+            filter: String::new(),
+            filter_case_sensitive: false,
+            scope_filter: None,
+            displayed: Vec::new(),
+            dragging: None,
+            collapsed_scopes: HashSet::new(),
+            selected: None,
+            edge_search: None,
+            bookmarks: Vec::new(),
+            bookmark_name: String::new(),
+            center_on: None,
+            segment_menu: None,
+            highlight_active: false,
+            show_overview: true,
+            snap_to_edges: true,
+            default_radix: Radix::default(),
+            scroll_rows: DEFAULT_SCROLL_ROWS,
+            palette: ColorScheme::default().palette(true),
+            line_width: *LINE_WIDTHS.start(),
+            value_column: false,
+            find_text: String::new(),
+            find_status: None,
+            go_to_text: String::new(),
+            go_to_status: None,
+            segments: Vec::new(),
+            samples: Vec::new(),
+            focused_row: None,
+            type_search: (String::new(), 0.0),
+        }
+    }
+
+    /// The radix a signal is displayed in.
+    fn radix(&self, id: Option<&str>) -> Radix {
+        id.and_then(|id| self.signal_settings.get(id))
+            .map_or(self.default_radix, |settings| settings.radix)
+    }
+
+    /// Names of the displayed signals and their values at the cursor, formatted in each signal's
+    /// radix.
+    ///
+    /// Returns `None` if the cursor is not placed, or is before the first timestamp. Signals whose
+    /// values can't be read are skipped.
+    fn cursor_values(&self) -> Option<Vec<(String, String)>> {
+        let ts = sample_at(self.index.timestamps(), self.cursor?)?;
+        let values = self
+            .displayed
+            .iter()
+            .filter_map(Displayed::signal)
+            .filter_map(|id| {
+                let value = self.vcd.value(id, ts)?;
+                let value = displayed_value(self.signal_settings.get(id), value);
+                let value = self.radix(Some(id)).format_value(&value);
+
+                Some((signal_name(&self.vcd, id), value))
+            })
+            .collect();
+
+        Some(values)
+    }
+
+    /// Path of the file that the view is saved to, or `None` if the file can't be reopened.
+    fn session_path(&self) -> Option<PathBuf> {
+        self.reloadable().then(|| {
+            let mut path = self.path.clone().into_os_string();
+            path.push(SESSION_EXTENSION);
+
+            PathBuf::from(path)
+        })
+    }
+
+    /// Save the view next to the file.
+    fn save_session(&self) {
+        let path = match self.session_path() {
+            Some(path) => path,
+            None => return,
+        };
+        let state = ViewState {
+            zoom: self.zoom,
+            view_time: self.view_time,
+            displayed: self.displayed.clone(),
+            signal_settings: self
+                .signal_settings
+                .iter()
+                .map(|(id, settings)| (id.clone(), SavedSignal::from(settings)))
+                .collect(),
+            collapsed_scopes: self.collapsed_scopes.clone(),
+            scope_filter: self.scope_filter.clone(),
+            selected: self.selected.clone(),
+            cursor: self.cursor,
+            marker_a: self.marker_a,
+            marker_b: self.marker_b,
+            bookmarks: self.bookmarks.clone(),
+        };
+
+        let result = ron::to_string(&state)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| std::fs::write(&path, contents).map_err(|err| err.to_string()));
+        if let Err(err) = result {
+            warn!("Unable to save view to `{}`: {err}", path.display());
+        }
+    }
+
+    /// Restore the view saved next to the file, if there is one.
+    ///
+    /// Signals that are no longer in the file are dropped.
+    fn restore_session(&mut self) {
+        let path = match self.session_path().filter(|path| path.is_file()) {
+            Some(path) => path,
+            None => return,
+        };
+        let state: ViewState = match std::fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| ron::from_str(&contents).map_err(|err| err.to_string()))
+        {
+            Ok(state) => state,
+            Err(err) => {
+                warn!("Unable to restore view from `{}`: {err}", path.display());
+                return;
+            }
+        };
+
+        let ids: HashSet<_> = self.vcd.signal_ids().into_iter().collect();
+        self.zoom = state.zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        self.scroll_time = Some(state.view_time);
+        self.displayed = state
+            .displayed
+            .into_iter()
+            .filter(|row| row.signal().is_none_or(|id| ids.contains(id)))
+            .collect();
+        self.signal_settings = state
+            .signal_settings
+            .into_iter()
+            .filter(|(id, _)| ids.contains(id))
+            .map(|(id, saved)| (id, SignalSettings::from(saved)))
+            .collect();
+        self.collapsed_scopes = state.collapsed_scopes;
+        self.scope_filter = state.scope_filter.filter(|scope| {
+            ids.iter()
+                .any(|id| in_scope(&signal_name(&self.vcd, id), scope))
+        });
+        self.selected = state.selected.filter(|id| ids.contains(id));
+        self.cursor = state.cursor;
+        self.marker_a = state.marker_a;
+        self.marker_b = state.marker_b;
+        self.bookmarks = state.bookmarks;
+    }
+
+    /// Save the displayed rows and their settings as a signal set.
+    fn save_signal_set(&self, path: &Path) -> Result<(), ExportError> {
+        let rows = self
+            .displayed
+            .iter()
+            .map(|row| match row {
+                Displayed::Signal(id) => SignalSetRow::Signal(
+                    signal_name(&self.vcd, id),
+                    self.signal_settings.get(id).map(SavedSignal::from),
+                ),
+                Displayed::Divider(label) => SignalSetRow::Divider(label.clone()),
+            })
+            .collect();
+
+        let config = ron::ser::PrettyConfig::default();
+        let contents = ron::ser::to_string_pretty(&SignalSet { rows }, config)
+            .map_err(|err| ExportError::Serialize(path.into(), err))?;
+        std::fs::write(path, contents).map_err(|err| ExportError::Write(path.into(), err))
+    }
+
+    /// Replace the displayed rows with those of a signal set, matching signals by full name.
+    ///
+    /// Returns the names of the signals in the set that are not in this file.
+    fn load_signal_set(&mut self, path: &Path) -> Result<Vec<String>, SignalSetError> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|err| SignalSetError::Read(path.into(), err))?;
+        let set: SignalSet =
+            ron::from_str(&contents).map_err(|err| SignalSetError::Parse(path.into(), err))?;
+
+        let ids: HashMap<_, _> = self
+            .vcd
+            .signal_ids()
+            .into_iter()
+            .map(|id| (signal_name(&self.vcd, &id), id))
+            .collect();
+        let mut displayed = Vec::new();
+        let mut missing = Vec::new();
+        for row in set.rows {
+            let row = match row {
+                SignalSetRow::Signal(name, saved) => match ids.get(&name) {
+                    Some(id) => {
+                        if let Some(saved) = saved {
+                            self.signal_settings.insert(id.clone(), saved.into());
+                        }
+                        Displayed::Signal(id.clone())
+                    }
+                    None => {
+                        missing.push(name);
+                        continue;
+                    }
+                },
+                SignalSetRow::Divider(label) => Displayed::Divider(label),
+            };
+            if !displayed.contains(&row) {
+                displayed.push(row);
+            }
+        }
+        self.displayed = displayed;
+        self.focused_row = None;
+
+        Ok(missing)
+    }
+
+    /// Reset the view to that of a newly opened file, and forget the saved view.
+    fn reset_view(&mut self) {
+        self.zoom = DEFAULT_ZOOM;
+        self.scroll_offset = Some(0.0);
+        self.displayed.clear();
+        self.signal_settings.clear();
+        self.collapsed_scopes.clear();
+        self.selected = None;
+        self.cursor = None;
+        self.marker_a = None;
+        self.marker_b = None;
+        self.bookmarks.clear();
+
+        if let Some(path) = self.session_path().filter(|path| path.is_file()) {
+            if let Err(err) = std::fs::remove_file(&path) {
+                warn!("Unable to remove saved view `{}`: {err}", path.display());
+            }
+        }
+    }
+
+    /// Move the cursor to the time in the go to text, and center the view on it.
+    ///
+    /// Times outside the dump are clamped to it. Returns an error message if the time is invalid.
+    fn go_to_time(&mut self) -> Result<(), String> {
+        let timestamps = self.index.timestamps();
+        let (first, last) = match (timestamps.first(), timestamps.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Err("No value changes recorded".to_string()),
+        };
+        let time = match self.timescale {
+            Some(timescale) => parse_time(&self.go_to_text, timescale)
+                .ok_or_else(|| "Enter a time, optionally with a unit, e.g. 1.5us".to_string())?,
+            None => self
+                .go_to_text
+                .trim()
+                .parse()
+                .map_err(|_| "Enter a number of ticks".to_string())?,
+        };
+        let time = time.clamp(first.get_value(), last.get_value());
+
+        self.cursor = Some(time);
+        self.center_on = Some(time);
+
+        Ok(())
+    }
+
+    /// Move the cursor to the next or previous time the selected signal starts matching the find
+    /// text, parsed in the signal's radix.
+    fn find_value(&mut self, forward: bool) {
+        let id = match self.selected.as_deref() {
+            Some(id) => id,
+            None => {
+                self.find_status = Some("Select a signal to search.".to_string());
+                return;
+            }
+        };
+        let radix = self.radix(Some(id));
+        let pattern = match radix.parse_pattern(&self.find_text) {
+            Some(pattern) => pattern,
+            None => {
+                self.find_status = Some(format!("Not a valid {radix} value."));
+                return;
+            }
+        };
+
+        let time = self
+            .cursor
+            .unwrap_or(if forward { i64::MIN } else { i64::MAX });
+        let reverse = self
+            .signal_settings
+            .get(id)
+            .is_some_and(|settings| settings.reverse_bits);
+        match find_value(&self.vcd, &self.index, id, time, forward, reverse, &pattern) {
+            Some(time) => {
+                self.cursor = Some(time);
+                self.center_on = Some(time);
+                self.find_status = None;
+            }
+            None => self.find_status = Some("No match.".to_string()),
+        }
+    }
+
+    /// Bookmark the cursor time, with the name entered in the Bookmarks menu.
+    fn add_bookmark(&mut self) {
+        let time = match self.cursor {
+            Some(time) => time,
+            None => return,
+        };
+        let name = match self.bookmark_name.trim() {
+            "" => format!("Bookmark {}", self.bookmarks.len() + 1),
+            name => name.to_string(),
+        };
+        self.bookmark_name.clear();
+
+        let index = self.bookmarks.partition_point(|(_, other)| *other <= time);
+        self.bookmarks.insert(index, (name, time));
+    }
+
+    /// The file name, for tab and status bar labels.
+    fn title(&self) -> String {
+        if let Some(live) = &self.live {
+            return live.address.clone();
+        }
+        if !self.reloadable() {
+            return "<stdin>".to_string();
+        }
+
+        self.path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+    }
+
+    /// Whether the file can be read again. Standard input and streams can only be read once.
+    fn reloadable(&self) -> bool {
+        self.path != Path::new(STDIN_PATH) && self.live.is_none()
+    }
+
+    /// Take the latest dump received from a live stream, centering its latest time when following
+    /// it.
+    ///
+    /// Returns `true` while the stream is connected.
+    fn poll_stream(&mut self) -> bool {
+        let connection = match self.live.as_ref().and_then(|live| live.connection.as_ref()) {
+            Some(connection) => connection,
+            None => return false,
+        };
+
+        // Each dump is complete, so only the latest one is needed
+        let mut waveform = None;
+        let mut closed = None;
+        for update in connection.try_iter() {
+            match update {
+                Update::Waveform(update) => waveform = Some(update),
+                Update::Closed(err) => closed = Some(err),
+            }
+        }
+
+        let live = self.live.as_mut().unwrap();
+        if let Some(err) = closed {
+            live.connection = None;
+            if let Some(err) = err {
+                warn!("{err}");
+                live.error = Some(err.to_string());
+            }
+        }
+        let connected = live.connection.is_some();
+        let follow = live.follow;
+
+        if let Some(waveform) = waveform {
+            self.replace(waveform, None);
+            if follow {
+                self.center_on = self.index.timestamps().last().map(Timestamp::get_value);
+            }
+        }
+
+        connected
+    }
+
+    /// Replace the waveforms with a reloaded copy, keeping the view.
+    ///
+    /// Displayed signals that no longer exist are dropped. Dividers are kept.
+    fn replace(&mut self, waveform: Waveform, file_stamp: Option<FileStamp>) {
+        let (vcd, header, dumpoff) = waveform.into_parts();
+        let ids = vcd.signal_ids();
+        self.displayed.retain(|row| {
+            row.signal()
+                .is_none_or(|id| ids.iter().any(|known| known == id))
+        });
+        self.index = Index::new(&vcd);
+        self.vcd = vcd;
+
+        // A unit chosen by the user is kept until the file declares a valid timescale
+        if let Some(timescale) = header.timescale.as_deref().and_then(parse_timescale) {
+            self.timescale = Some(timescale);
+            self.timescale_valid = true;
+        }
+        self.header = header;
+        self.dumpoff = dumpoff;
+        self.file_stamp = file_stamp;
+        self.file_changed = false;
+    }
+
+    /// Run a command from the command palette that acts on this document.
+    fn run_command(&mut self, command: Command) {
+        let edge_search = |forward, any_signal| {
+            Some(EdgeSearch {
+                forward,
+                any_signal,
+            })
+        };
+
+        match command {
+            Command::ZoomToFit => self.zoom_to = Some(ZoomTarget::All),
+            Command::ZoomToSelection => self.zoom_to = Some(ZoomTarget::Markers),
+            Command::ResetView => self.reset_view(),
+            Command::PreviousEdge => self.edge_search = edge_search(false, false),
+            Command::NextEdge => self.edge_search = edge_search(true, false),
+            Command::PreviousChange => self.edge_search = edge_search(false, true),
+            Command::NextChange => self.edge_search = edge_search(true, true),
+            Command::SetMarkerA => self.marker_a = self.cursor,
+            Command::SetMarkerB => self.marker_b = self.cursor,
+            Command::ClearMarkers => {
+                self.marker_a = None;
+                self.marker_b = None;
+            }
+            Command::AddBookmark => self.add_bookmark(),
+            _ => (),
+        }
+    }
+
+    /// Add the navigation items for this document to the View menu.
+    fn view_menu(&mut self, ui: &mut Ui) {
+        ui.checkbox(&mut self.highlight_active, "Highlight Active Signals")
+            .on_hover_text("Dim signals that don't change in the visible time range");
+        ui.checkbox(&mut self.show_overview, "Show Overview")
+            .on_hover_text("Show the whole time range below the timeline");
+        ui.checkbox(&mut self.snap_to_edges, "Snap Cursor to Edges")
+            .on_hover_text("Place the cursor on a nearby edge of the clicked signal");
+
+        ui.separator();
+        let button = egui::Button::new("Zoom to Fit").shortcut_text("F");
+        if ui.add(button).clicked() {
+            self.zoom_to = Some(ZoomTarget::All);
+            ui.close_menu();
+        }
+        let button = egui::Button::new("Zoom to Selection").shortcut_text("Shift+F");
+        let markers = self.marker_a.is_some() && self.marker_b.is_some();
+        if ui.add_enabled(markers, button).clicked() {
+            self.zoom_to = Some(ZoomTarget::Markers);
+            ui.close_menu();
+        }
+        if ui
+            .button("Reset View")
+            .on_hover_text("Forget the signals, markers, and zoom saved for this file")
+            .clicked()
+        {
+            self.reset_view();
+            ui.close_menu();
+        }
+
+        ui.separator();
+        let edges = [
+            ("Previous Edge", "Left", false, false),
+            ("Next Edge", "Right", true, false),
+            ("Previous Change", "Shift+Left", false, true),
+            ("Next Change", "Shift+Right", true, true),
+        ];
+        for (label, shortcut, forward, any_signal) in edges {
+            let button = egui::Button::new(label).shortcut_text(shortcut);
+            if ui.add(button).clicked() {
+                self.edge_search = Some(EdgeSearch {
+                    forward,
+                    any_signal,
+                });
+                ui.close_menu();
+            }
+        }
+
+        ui.separator();
+        let button = egui::Button::new("Set Marker A").shortcut_text("A");
+        if ui.add_enabled(self.cursor.is_some(), button).clicked() {
+            self.marker_a = self.cursor;
+            ui.close_menu();
+        }
+        let button = egui::Button::new("Set Marker B").shortcut_text("B");
+        if ui.add_enabled(self.cursor.is_some(), button).clicked() {
+            self.marker_b = self.cursor;
+            ui.close_menu();
+        }
+        let button = egui::Button::new("Clear Markers").shortcut_text("Esc");
+        if ui.add(button).clicked() {
+            self.marker_a = None;
+            self.marker_b = None;
+            ui.close_menu();
+        }
+
+        ui.separator();
+        ui.menu_button("Bookmarks", |ui| {
+            ui.horizontal(|ui| {
+                let name = egui::TextEdit::singleline(&mut self.bookmark_name)
+                    .hint_text("Name")
+                    .desired_width(120.0);
+                ui.add(name);
+                let button = egui::Button::new("Add at Cursor").shortcut_text("M");
+                if ui.add_enabled(self.cursor.is_some(), button).clicked() {
+                    self.add_bookmark();
+                }
+            });
+
+            let timescale = self.timescale;
+            let mut remove = None;
+            for (index, (name, time)) in self.bookmarks.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    let label = format!("{name} @ {}", time_label(*time, timescale));
+                    if ui.button(label).clicked() {
+                        self.cursor = Some(*time);
+                        self.center_on = Some(*time);
+                        ui.close_menu();
+                    }
+                    if ui.small_button("×").on_hover_text("Remove").clicked() {
+                        remove = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = remove {
+                self.bookmarks.remove(index);
+            }
+        });
+    }
+
+    /// Write the displayed signals to a CSV file, with one row per timestamp.
+    ///
+    /// Values are formatted with each signal's radix. Rows are written as they are formatted, so
+    /// large dumps are never held in memory as text.
+    fn export_csv(&self, path: &Path) -> Result<(), ExportError> {
+        let vcd = &self.vcd;
+        let write_err = |err| ExportError::Write(path.into(), err);
+
+        let file = std::fs::File::create(path).map_err(write_err)?;
+        let mut writer = BufWriter::new(file);
+
+        let ids: Vec<_> = self
+            .displayed
+            .iter()
+            .filter_map(Displayed::signal)
+            .collect();
+        let names = ids.iter().map(|id| csv_field(&signal_name(vcd, id)));
+        let header = std::iter::once("time".to_string())
+            .chain(names)
+            .collect::<Vec<_>>()
+            .join(",");
+        writeln!(writer, "{header}").map_err(write_err)?;
+
+        for &ts in self.index.timestamps() {
+            write!(writer, "{}", ts.get_value()).map_err(write_err)?;
+            for &id in &ids {
+                let settings = self.signal_settings.get(id);
+                let radix = settings.map_or(self.default_radix, |settings| settings.radix);
+                // A value that can't be read leaves its field empty, keeping the columns aligned
+                let value = vcd
+                    .value(id, ts)
+                    .map(|value| radix.format_value(&displayed_value(settings, value)))
+                    .unwrap_or_default();
+                write!(writer, ",{}", csv_field(&value)).map_err(write_err)?;
+            }
+            writeln!(writer).map_err(write_err)?;
+        }
+
+        writer.flush().map_err(write_err)
+    }
+
+    /// Draw a summary of the file, the cursor and marker times, and the distance between the
+    /// markers.
+    ///
+    /// Returns `true` if the user asked to reload the file.
+    fn draw_status_bar(&mut self, ui: &mut Ui) -> bool {
+        let vcd = &self.vcd;
+        let timestamps = self.index.timestamps();
+        let signal_count = vcd.signal_ids().len();
+        let title = self.title();
+        let mut reload = false;
+
+        ui.horizontal(|ui| {
+            ui.label(title);
+            if let Some(live) = &mut self.live {
+                ui.separator();
+                match (&live.connection, &live.error) {
+                    (Some(_), _) => ui.label("● Live"),
+                    (None, Some(error)) => ui
+                        .colored_label(ui.visuals().warn_fg_color, "⚠ Connection lost")
+                        .on_hover_text(error.as_str()),
+                    (None, None) => ui.weak("Disconnected"),
+                };
+                ui.checkbox(&mut live.follow, "Follow")
+                    .on_hover_text("Center the latest time as it arrives");
+            }
+            ui.separator();
+            ui.label(format!("{signal_count} signals"));
+            ui.separator();
+            ui.label(format!("{} timestamps", timestamps.len()));
+            if let Some(timescale) = &self.header.timescale {
+                ui.separator();
+                ui.label(format!("Timescale: {timescale}"));
+            }
+            if !self.timescale_valid {
+                ui.separator();
+                ui.colored_label(ui.visuals().warn_fg_color, "⚠ No valid timescale")
+                    .on_hover_text("Times are shown in ticks unless a unit is chosen");
+                egui::ComboBox::from_id_source("timescale")
+                    .selected_text(
+                        self.timescale
+                            .map_or("ticks", |timescale| unit_label(timescale.unit)),
+                    )
+                    .show_ui(ui, |ui| {
+                        if ui
+                            .selectable_label(self.timescale.is_none(), "ticks")
+                            .clicked()
+                        {
+                            self.timescale = None;
+                        }
+                        for scale in SCALES {
+                            let selected =
+                                self.timescale.map(|timescale| unit_label(timescale.unit))
+                                    == Some(unit_label(scale));
+                            if ui.selectable_label(selected, unit_label(scale)).clicked() {
+                                self.timescale = Some(scale.into());
+                            }
+                        }
+                    });
+            }
+
+            if let (Some(first), Some(last)) = (timestamps.first(), timestamps.last()) {
+                let timescale = self.timescale;
+                ui.separator();
+                ui.label(format!(
+                    "{} - {}",
+                    time_label(first.get_value(), timescale),
+                    time_label(last.get_value(), timescale),
+                ));
+
+                ui.separator();
+                let markers = [
+                    ("Cursor", self.cursor),
+                    ("A", self.marker_a),
+                    ("B", self.marker_b),
+                ];
+                for (name, time) in markers {
+                    match time {
+                        Some(time) => ui.label(format!("{name}: {}", time_label(time, timescale))),
+                        None => ui.weak(format!("{name}: -")),
+                    };
+                }
+
+                if let (Some(a), Some(b)) = (self.marker_a, self.marker_b) {
+                    let delta = b - a;
+                    let mut text = format!("B - A: {}", time_label(delta, timescale));
+                    if let Some(timescale) = timescale.filter(|_| delta != 0) {
+                        let period = delta.unsigned_abs() as f64 * timescale.seconds();
+                        text.push_str(&format!(" ({})", format_frequency(1.0 / period)));
+                    }
+                    ui.separator();
+                    ui.label(text);
+                }
+            }
+
+            if self.file_changed {
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    reload = ui.button("Reload").clicked();
+                    ui.label("The file has changed on disk.");
+                });
+            }
+        });
+
+        reload
+    }
+
+    /// Draw the list of all signals, grouped by scope.
+    ///
+    /// Checked signals are displayed in the waveform view.
+    fn draw_signal_list(&mut self, ui: &mut Ui) {
+        let vcd = &self.vcd;
+
+        ui.horizontal(|ui| {
+            ui.label("Filter:");
+            ui.add(egui::TextEdit::singleline(&mut self.filter).hint_text("name, *glob?"));
+        });
+        ui.checkbox(&mut self.filter_case_sensitive, "Match case");
+
+        let names: Vec<_> = vcd
+            .signal_ids()
+            .into_iter()
+            .map(|id| (signal_name(vcd, &id), id))
+            .collect();
+        ui.horizontal(|ui| {
+            ui.label("Scope:");
+            egui::ComboBox::from_id_source("scope_filter")
+                .selected_text(self.scope_filter.as_deref().unwrap_or("All"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.scope_filter, None, "All");
+                    for scope in signal_scopes(names.iter().map(|(name, _)| name.as_str())) {
+                        let label = scope.clone();
+                        ui.selectable_value(&mut self.scope_filter, Some(scope), label);
+                    }
+                });
+            if let Some(scope) = &self.scope_filter {
+                let parent = scope.rsplit_once('.').map(|(parent, _)| parent.to_string());
+                if ui.small_button("↑").on_hover_text("Parent scope").clicked() {
+                    self.scope_filter = parent;
+                }
+            }
+        });
+
+        let signals: Vec<_> = names
+            .into_iter()
+            .filter(|(name, _)| {
+                self.scope_filter
+                    .as_deref()
+                    .is_none_or(|scope| in_scope(name, scope))
+                    && matches_filter(name, &self.filter, self.filter_case_sensitive)
+            })
+            .collect();
+
+        ui.horizontal(|ui| {
+            if ui.button("Display All").clicked() {
+                for (_, id) in &signals {
+                    let row = Displayed::Signal(id.clone());
+                    if !self.displayed.contains(&row) {
+                        self.displayed.push(row);
+                    }
+                }
+            }
+            if ui.button("Clear").clicked() {
+                self.displayed.clear();
+            }
+        });
+        ui.separator();
+
+        if signals.is_empty() {
+            ui.weak("No matching signals");
+            return;
+        }
+
+        let rows = signal_rows(signals.into_iter(), &self.collapsed_scopes);
+        let row_height = ui.spacing().interact_size.y;
+        egui::ScrollArea::both()
+            .auto_shrink([false, false])
+            .show_rows(ui, row_height, rows.len(), |ui, range| {
+                for row in &rows[range] {
+                    ui.horizontal(|ui| {
+                        ui.add_space(row.depth() as f32 * SCOPE_INDENT);
+                        match row {
+                            // Expand or collapse the scope
+                            Row::Scope { path, label, .. } => {
+                                if ui.selectable_label(false, label).clicked()
+                                    && !self.collapsed_scopes.remove(path)
+                                {
+                                    self.collapsed_scopes.insert(path.clone());
+                                }
+                            }
+                            Row::Signal { id, label, .. } => {
+                                let mut checked = self
+                                    .displayed
+                                    .iter()
+                                    .any(|row| row.signal() == Some(id.as_str()));
+                                if ui.checkbox(&mut checked, label).changed() {
+                                    if checked {
+                                        self.displayed.push(Displayed::Signal(id.clone()));
+                                    } else {
+                                        self.displayed
+                                            .retain(|row| row.signal() != Some(id.as_str()));
+                                    }
+                                }
+                            }
+                        }
+                    });
+                }
+            });
+    }
+
+    /// Draw the VCD waveforms.
+    ///
+    /// Keyboard navigation only applies to the `focused` view. Rows fit the signal names unless a
+    /// `row_height` is given.
+    fn draw_vcd(&mut self, ui: &mut Ui, focused: bool, row_height: Option<f32>) {
+        let vcd = &self.vcd;
+        let index = &self.index;
+
+        // The last sample is held for a single time unit.
+        let timestamps = index.timestamps();
+        let timescale = self.timescale;
+        let (start, end) = match (timestamps.first(), timestamps.last()) {
+            (Some(first), Some(last)) => (first.get_value(), last.get_value() + 1),
+            _ => {
+                draw_message(ui, "No value changes recorded.");
+                return;
+            }
+        };
+
+        if vcd.signal_ids().is_empty() {
+            draw_message(ui, "This VCD contains no signals.");
+            return;
+        }
+        if self.displayed.is_empty() {
+            draw_message(ui, "Check signals in the signal list to display them.");
+            return;
+        }
+
+        // Values of the displayed signals at the cursor, formatted in each signal's radix
+        let cursor_sample = self.cursor.and_then(|time| sample_at(timestamps, time));
+        let values: Vec<_> = self
+            .displayed
+            .iter()
+            .map(|row| {
+                let (id, ts) = (row.signal()?, cursor_sample?);
+                let settings = self.signal_settings.get(id);
+                let radix = settings.map_or(self.default_radix, |settings| settings.radix);
+                let value = displayed_value(settings, vcd.value(id, ts)?);
+
+                Some(radix.format_value(&value))
+            })
+            .collect();
+
+        // Annotate signal names with their values, unless the values have their own column
+        let signals: Vec<_> = self
+            .displayed
+            .iter()
+            .zip(&values)
+            .map(|(row, value)| {
+                let id = match row {
+                    Displayed::Signal(id) => id,
+                    Displayed::Divider(label) => return (label.clone(), None),
+                };
+                let name = self
+                    .signal_settings
+                    .get(id)
+                    .and_then(|settings| settings.alias.clone())
+                    .unwrap_or_else(|| signal_name(vcd, id));
+                let label = match value {
+                    Some(value) if !self.value_column => format!("{name} = {value}"),
+                    _ => name,
+                };
+
+                (label, Some(id.clone()))
+            })
+            .collect();
+
+        // The value column is part of the pinned name column, after the widest name
+        let sense = Sense::hover();
+        let mut size = get_max_string_size(ui, signals.iter().map(|(name, _)| name));
+        let value_x = size.x;
+        if self.value_column {
+            size.x += get_max_string_size(ui, values.iter().flatten()).x;
+        }
+        let name_width = size.x + ui.spacing().item_spacing.x;
+
+        // Rows shorter than the names use small text, and can't be shorter than that
+        let header_height = size.y;
+        let name_style = match row_height {
+            Some(height) if height < size.y => egui::TextStyle::Small,
+            _ => egui::TextStyle::Body,
+        };
+        if let Some(height) = row_height {
+            size.y = height.max(ui.text_style_height(&egui::TextStyle::Small));
+        }
+
+        // Fit the requested time range to the width of the waveform column
+        let range = match self.zoom_to.take() {
+            Some(ZoomTarget::All) => Some((start, end)),
+            Some(ZoomTarget::Range(from, to)) => Some((from.unwrap_or(start), to.unwrap_or(end))),
+            Some(ZoomTarget::Markers) => self
+                .marker_a
+                .zip(self.marker_b)
+                .map(|(a, b)| (a.min(b), a.max(b))),
+            None => None,
+        };
+        let available = ui.available_width() - name_width - ui.spacing().scroll_bar_width;
+        let mut target = None;
+        if let Some((from, to)) = range.filter(|(from, to)| to > from) {
+            let zoom = (available / (to - from) as f32).clamp(MIN_ZOOM, MAX_ZOOM);
+            target = Some((zoom, from as f64));
+        }
+
+        // Center a bookmark that was jumped to
+        if let Some(time) = self.center_on.take() {
+            let zoom = target.map_or(self.zoom, |(zoom, _)| zoom);
+            let left = time as f64 - (available / 2.0 / zoom) as f64;
+            target = Some((zoom, left.max(start as f64)));
+        }
+
+        // Move to the target at once, or start a transition to it
+        let now = ui.input(|i| i.time);
+        if let Some(to) = target {
+            self.animation = Some(ViewAnimation {
+                from: (self.zoom, self.view_time),
+                to,
+                started: now,
+            });
+        }
+        if let Some(animation) = self.animation {
+            let t = if self.animate {
+                ((now - animation.started) / VIEW_ANIMATION_TIME).min(1.0)
+            } else {
+                1.0
+            };
+            let (zoom, time) = animation.at(t);
+            self.zoom = zoom;
+            self.scroll_offset = Some(((time - start as f64) as f32 * zoom).max(0.0));
+            if t < 1.0 {
+                ui.ctx().request_repaint();
+            } else {
+                self.animation = None;
+            }
+        }
+
+        // Align with a linked view
+        if let Some(time) = self.scroll_time.take() {
+            let offset = (time - start as f64) as f32 * self.zoom;
+            self.scroll_offset = Some(offset.max(0.0));
+        }
+
+        let zoom = self.zoom;
+        let waveform_size = Vec2::new((end - start) as f32 * zoom, size.y);
+
+        // Allocate space for the timeline header; it is drawn after the scroll offset is known
+        let header_size = Vec2::new(ui.available_width(), header_height);
+        let (header_rect, header_response) = ui.allocate_exact_size(header_size, sense);
+        let overview = self.show_overview.then(|| {
+            let size = Vec2::new(ui.available_width(), OVERVIEW_HEIGHT);
+            ui.allocate_exact_size(size, Sense::click_and_drag())
+        });
+
+        let mut scroll_area = egui::ScrollArea::both().auto_shrink([false, false]);
+        if let Some(offset) = self.scroll_offset.take() {
+            scroll_area = scroll_area.horizontal_scroll_offset(offset);
+        }
+
+        let row_height = size.y + ui.spacing().item_spacing.y;
+
+        // The mouse wheel scrolls by whole rows, or through time while holding Shift. The scroll
+        // delta is taken before the scroll area can apply it; the sign follows the OS setting.
+        let mut scroll = Vec2::ZERO;
+        if ui.rect_contains_pointer(ui.available_rect_before_wrap()) {
+            scroll = ui.input_mut(|i| std::mem::take(&mut i.scroll_delta));
+            if scroll != Vec2::ZERO {
+                self.animation = None;
+            }
+            if ui.input(|i| i.modifiers.shift) {
+                scroll = Vec2::new(scroll.x + scroll.y, 0.0);
+            } else {
+                let rows = scroll.y / POINTS_PER_SCROLL_LINE * self.scroll_rows as f32;
+                scroll.y = rows * row_height;
+            }
+        }
+
+        // Move the keyboard focus through the rows with the arrow keys or by typing a name, and
+        // select the focused signal. Shortcuts for single letters are typed into the search while
+        // a row has the focus.
+        let row_focus = focused && self.focused_row.is_some();
+        let mut scroll_to_focus = false;
+        if focused && !ui.ctx().wants_keyboard_input() {
+            let (up, down, select, escape, text, time) = ui.input(|i| {
+                let text: String = i
+                    .events
+                    .iter()
+                    .filter_map(|event| match event {
+                        egui::Event::Text(text) => Some(text.trim()),
+                        _ => None,
+                    })
+                    .collect();
+
+                (
+                    i.key_pressed(Key::ArrowUp),
+                    i.key_pressed(Key::ArrowDown),
+                    i.key_pressed(Key::Space) || i.key_pressed(Key::Enter),
+                    i.key_pressed(Key::Escape),
+                    text,
+                    i.time,
+                )
+            });
+            let last = signals.len() - 1;
+
+            if up || down {
+                let row = match self.focused_row {
+                    Some(row) if up => row.saturating_sub(1),
+                    Some(row) => row + 1,
+                    None => signals
+                        .iter()
+                        .position(|(_, id)| id.is_some() && *id == self.selected)
+                        .unwrap_or(0),
+                };
+                self.focused_row = Some(row.min(last));
+                scroll_to_focus = true;
+            }
+            if let Some(row) = self.focused_row.filter(|_| row_focus) {
+                let row = row.min(last);
+                if let (true, Some(id)) = (select, &signals[row].1) {
+                    self.selected = Some(id.clone());
+                }
+                if escape {
+                    self.focused_row = None;
+                }
+
+                if !text.is_empty() {
+                    let (query, last_key) = &mut self.type_search;
+                    if time - *last_key > TYPE_SEARCH_TIMEOUT {
+                        query.clear();
+                    }
+                    query.push_str(&text.to_lowercase());
+                    *last_key = time;
+
+                    let found = signals
+                        .iter()
+                        .position(|(label, _)| label.to_lowercase().contains(query.as_str()));
+                    if let Some(row) = found {
+                        self.focused_row = Some(row);
+                        scroll_to_focus = true;
+                    }
+                }
+            }
+        }
+
+        let output = scroll_area.show_viewport(ui, |ui, viewport| {
+            ui.set_width(name_width + waveform_size.x);
+            ui.set_height(row_height * signals.len() as f32);
+            if scroll != Vec2::ZERO {
+                ui.scroll_with_delta(scroll);
+            }
+            if let Some(row) = self.focused_row.filter(|_| scroll_to_focus) {
+                let origin = ui.max_rect().min;
+                let min = Pos2::new(
+                    origin.x + viewport.min.x,
+                    origin.y + row as f32 * row_height,
+                );
+                let rect = Rect::from_min_size(min, Vec2::new(viewport.width(), row_height));
+                ui.scroll_to_rect(rect, None);
+            }
+
+            // Only the rows and samples inside the viewport are drawn
+            let origin = ui.max_rect().min;
+            let first_row = (viewport.min.y / row_height).floor().max(0.0) as usize;
+            let last_row = ((viewport.max.y / row_height).ceil() as usize).min(signals.len());
+
+            // The name column is pinned to the left edge of the viewport
+            let visible_start = start + (viewport.min.x / zoom).floor() as i64;
+            let visible_end = start + ((viewport.max.x - name_width) / zoom).ceil() as i64;
+
+            // Hatch the ranges in which values were not recorded, behind the waveforms
+            let column = Rect::from_x_y_ranges(
+                origin.x + viewport.min.x + name_width..=origin.x + viewport.max.x,
+                origin.y + viewport.min.y..=origin.y + viewport.max.y,
+            );
+            let timeline = Timeline {
+                origin: origin.x + name_width,
+                start,
+                zoom,
+            };
+            for range in &self.dumpoff {
+                let x_range = timeline.x(range.start)..=timeline.x(range.end);
+                let rect = Rect::from_x_y_ranges(x_range, column.y_range()).intersect(column);
+                draw_hatched(ui.painter(), rect, DUMPOFF_COLOR);
+            }
+
+            for (i, (name, id)) in signals.iter().enumerate().take(last_row).skip(first_row) {
+                let top = origin.y + i as f32 * row_height;
+                let mut rect = Rect::from_min_size(Pos2::new(origin.x + viewport.min.x, top), size);
+                let mut response =
+                    ui.interact(rect, ui.id().with(("name", i)), Sense::click_and_drag());
+                let spacing_x = ui.spacing().item_spacing.x;
+
+                // Dividers are headings across all columns, without a waveform
+                let id = match id {
+                    Some(id) => id,
+                    None => {
+                        let painter = ui.painter();
+                        let text_rect = painter.text(
+                            Pos2::new(rect.min.x + spacing_x, rect.center().y),
+                            egui::Align2::LEFT_CENTER,
+                            name,
+                            name_style.resolve(ui.style()),
+                            ui.visuals().strong_text_color(),
+                        );
+                        painter.hline(
+                            text_rect.max.x + spacing_x..=origin.x + viewport.max.x,
+                            rect.center().y,
+                            ui.visuals().widgets.noninteractive.bg_stroke,
+                        );
+                        if self.focused_row == Some(i) {
+                            let stroke = ui.visuals().selection.stroke;
+                            painter.rect_stroke(rect.shrink(1.0), 2.0, stroke);
+                        }
+
+                        response.context_menu(|ui| {
+                            if let Some(Displayed::Divider(label)) = self.displayed.get_mut(i) {
+                                ui.horizontal(|ui| {
+                                    ui.label("Label:");
+                                    ui.text_edit_singleline(label);
+                                });
+                            }
+                            if ui.button("Remove Divider").clicked() {
+                                self.displayed.remove(i);
+                                ui.close_menu();
+                            }
+                        });
+                        if response.drag_started() {
+                            self.dragging = Some(i);
+                        }
+                        continue;
+                    }
+                };
+                let aliased = self
+                    .signal_settings
+                    .get(id)
+                    .is_some_and(|settings| settings.alias.is_some());
+                if aliased {
+                    response = response.on_hover_text(signal_name(vcd, id));
+                }
+
+                let highlight_color = egui::Color32::from_additive_luminance(15);
+                let selection_color = ui.style().visuals.selection.bg_fill;
+
+                // Draw the background for odd rows across the visible width of all columns
+                let half_spacing = ui.spacing().item_spacing.y / 2.0;
+                let row_rect = Rect::from_min_max(
+                    Pos2::new(origin.x + viewport.min.x, top - half_spacing),
+                    Pos2::new(origin.x + viewport.max.x, top + size.y + half_spacing),
+                );
+                let painter = ui.painter();
+                if i % 2 != 0 {
+                    painter.rect_filled(row_rect, 0.0, highlight_color);
+                }
+
+                // The name column is painted in its own clip rect, on top of the waveform
+                let name_clip = Rect::from_x_y_ranges(
+                    row_rect.min.x..=row_rect.min.x + name_width,
+                    row_rect.y_range(),
+                );
+                let name_painter = ui.painter_at(name_clip);
+                if self.selected.as_ref() == Some(id) {
+                    name_painter.rect_filled(name_clip, 0.0, selection_color);
+                }
+
+                // Draw waveform
+                let waveform_rect =
+                    Rect::from_min_size(Pos2::new(origin.x + name_width, top), waveform_size);
+                let waveform_clip = Rect::from_x_y_ranges(
+                    row_rect.min.x + name_width..=row_rect.max.x,
+                    row_rect.y_range(),
+                );
+                let waveform_response = ui.interact(
+                    waveform_rect.intersect(waveform_clip),
+                    ui.id().with(("waveform", i)),
+                    Sense::click(),
+                );
+                let timeline = Timeline {
+                    origin: waveform_rect.min.x,
+                    start,
+                    zoom,
+                };
+
+                // Place the cursor, snapped to a nearby edge of this signal or the nearest sample
+                if waveform_response.clicked() {
+                    if let Some(pos) = waveform_response.interact_pointer_pos() {
+                        let time = timeline.time(pos.x);
+                        let edge = self
+                            .snap_to_edges
+                            .then(|| nearest_edge(vcd, index, id, time, zoom))
+                            .flatten();
+                        self.cursor = edge
+                            .or_else(|| nearest_timestamp(timestamps, time))
+                            .or(Some(time));
+                    }
+                }
+
+                let settings = self.signal_settings.get(id);
+                let radix = settings.map_or(self.default_radix, |settings| settings.radix);
+                let plot = settings.map_or(Plot::Digital, |settings| settings.plot);
+                let style = WaveformStyle {
+                    width: self.line_width,
+                    color: settings
+                        .and_then(|settings| settings.color)
+                        .unwrap_or(self.palette.waveform),
+                    highz: self.palette.highz,
+                    unknown: self.palette.unknown,
+                };
+                self.segments.clear();
+                self.segments.extend(
+                    index
+                        .segments(vcd, id, visible_start, visible_end, end)
+                        .map(|(from, to, value)| (from, to, displayed_value(settings, value))),
+                );
+                let segments = &self.segments;
+                let active = segments.len() > 1;
+
+                // Show the value of the segment under the pointer
+                let hover_time = waveform_response
+                    .hover_pos()
+                    .map(|pos| timeline.time(pos.x));
+                let hovered = hover_time.and_then(|time| {
+                    segments
+                        .iter()
+                        .find(|(from, to, _)| (*from..*to).contains(&time))
+                });
+
+                // Center or zoom the view on the right-clicked segment
+                if waveform_response.secondary_clicked() {
+                    self.segment_menu = hovered.map(|(from, to, _)| (*from, *to));
+                }
+                let segment_menu = self.segment_menu;
+                let waveform_response = waveform_response.context_menu(|ui| {
+                    let button = egui::Button::new("Center View Here");
+                    if ui
+                        .add_enabled(segment_menu.is_some(), button)
+                        .on_hover_text("Center the view on the start of this value")
+                        .clicked()
+                    {
+                        self.center_on = segment_menu.map(|(from, _)| from);
+                        ui.close_menu();
+                    }
+                    let button = egui::Button::new("Zoom to Segment");
+                    if ui
+                        .add_enabled(segment_menu.is_some(), button)
+                        .on_hover_text("Fit this value in the view")
+                        .clicked()
+                    {
+                        self.zoom_to =
+                            segment_menu.map(|(from, to)| ZoomTarget::Range(Some(from), Some(to)));
+                        ui.close_menu();
+                    }
+                });
+
+                if let Some((from, to, value)) = hovered {
+                    let text = format!(
+                        "{} = {} @ {}..{}",
+                        signal_name(vcd, id),
+                        radix.format_value(value),
+                        time_label(*from, timescale),
+                        time_label(*to, timescale),
+                    );
+                    waveform_response.on_hover_ui_at_pointer(|ui| {
+                        ui.label(text);
+                    });
+                }
+
+                // Waveforms are clipped to their column, so bus labels never overlap the names
+                let painter = ui.painter_at(waveform_clip);
+                self.samples.clear();
+                self.samples
+                    .extend(self.segments.drain(..).map(|(from, to, value)| {
+                        let rect = Rect::from_min_max(
+                            Pos2::new(timeline.x(from), waveform_rect.min.y),
+                            Pos2::new(timeline.x(to), waveform_rect.max.y),
+                        );
+
+                        (rect, value)
+                    }));
+                let samples = &self.samples;
+                let single_bit = samples.first().is_some_and(
+                    |(_, value)| matches!(value, SignalValue::Literal(bits, _) if bits.len() == 1),
+                );
+                if plot == Plot::Text {
+                    draw_value_text(&painter, samples, radix, style);
+                } else if plot != Plot::Digital {
+                    let range = settings.and_then(|settings| settings.plot_range);
+                    draw_analog_waveform(&painter, samples, radix, plot, range, style);
+                } else if single_bit {
+                    draw_bit_waveform(&painter, samples, style);
+                } else {
+                    for (rect, value) in samples {
+                        draw_waveform_sample(&painter, *rect, value, radix, style);
+                    }
+                }
+
+                // Draw signal name with fixed X position and width
+                let text_galley = ui.fonts(|fonts| {
+                    let font = egui::FontSelection::Style(name_style.clone());
+                    egui::WidgetText::from(name)
+                        .into_text_job(ui.style(), font, egui::Align::LEFT)
+                        .into_galley(fonts)
+                });
+                rect.min.x += spacing_x;
+                rect.min.y += ((size.y - text_galley.size().y) / 2.0).max(0.0);
+                let text_color = if self.highlight_active && !active {
+                    ui.visuals().weak_text_color()
+                } else {
+                    ui.visuals().text_color()
+                };
+                name_painter.galley_with_color(rect.min, text_galley.galley, text_color);
+                if let Some(value) = values[i].as_ref().filter(|_| self.value_column) {
+                    name_painter.text(
+                        Pos2::new(rect.min.x + value_x, rect.min.y),
+                        egui::Align2::LEFT_TOP,
+                        value,
+                        name_style.resolve(ui.style()),
+                        text_color,
+                    );
+                }
+                if self.focused_row == Some(i) {
+                    let stroke = ui.visuals().selection.stroke;
+                    name_painter.rect_stroke(name_clip.shrink(1.0), 2.0, stroke);
+                }
+
+                // Select the signal for edge navigation
+                if response.clicked() {
+                    self.selected = Some(id.clone());
+                }
+
+                // Per-signal settings
+                response.context_menu(|ui| {
+                    let settings =
+                        self.signal_settings
+                            .entry(id.clone())
+                            .or_insert_with(|| SignalSettings {
+                                radix: self.default_radix,
+                                ..Default::default()
+                            });
+                    ui.horizontal(|ui| {
+                        let mut alias = settings.alias.clone().unwrap_or_default();
+                        ui.label("Alias:");
+                        if ui.text_edit_singleline(&mut alias).changed() {
+                            settings.alias = Some(alias).filter(|alias| !alias.is_empty());
+                        }
+                        if ui
+                            .button("Reset")
+                            .on_hover_text("Show the full name")
+                            .clicked()
+                        {
+                            settings.alias = None;
+                        }
+                    });
+                    ui.menu_button("Radix", |ui| {
+                        for radix in Radix::ALL {
+                            let label = radix.to_string();
+                            if ui.radio_value(&mut settings.radix, radix, label).clicked() {
+                                ui.close_menu();
+                            }
+                        }
+
+                        ui.separator();
+                        ui.checkbox(&mut settings.reverse_bits, "Reverse Bit Order")
+                            .on_hover_text(
+                                "Read the most significant bit as the least significant",
+                            );
+                    });
+                    ui.horizontal(|ui| {
+                        let mut color = settings.color.unwrap_or(self.palette.waveform);
+                        ui.label("Color:");
+                        if ui.color_edit_button_srgba(&mut color).changed() {
+                            settings.color = Some(color);
+                        }
+                        if ui.button("Reset").clicked() {
+                            settings.color = None;
+                        }
+                    });
+                    if ui.button("Insert Divider Above").clicked() {
+                        let label = "Divider".to_string();
+                        self.displayed.insert(i, Displayed::Divider(label));
+                        ui.close_menu();
+                    }
+                    ui.menu_button("Plot", |ui| {
+                        let plots = [
+                            (Plot::Digital, "Digital"),
+                            (Plot::Step, "Analog Step"),
+                            (Plot::Line, "Analog Line"),
+                            (Plot::Text, "Value Text"),
+                        ];
+                        for (plot, label) in plots {
+                            if ui.radio_value(&mut settings.plot, plot, label).clicked() {
+                                ui.close_menu();
+                            }
+                        }
+
+                        ui.separator();
+                        let mut auto = settings.plot_range.is_none();
+                        if ui.checkbox(&mut auto, "Fit Visible Values").changed() {
+                            settings.plot_range = if auto { None } else { Some((0.0, 255.0)) };
+                        }
+                        if let Some((min, max)) = settings.plot_range.as_mut() {
+                            ui.horizontal(|ui| {
+                                ui.label("Min:");
+                                ui.add(egui::DragValue::new(min));
+                                ui.label("Max:");
+                                ui.add(egui::DragValue::new(max));
+                            });
+                        }
+                    });
+
+                    ui.separator();
+                    let button = egui::Button::new("Copy Value at Cursor");
+                    if ui.add_enabled(cursor_sample.is_some(), button).clicked() {
+                        if let Some(value) = cursor_sample.and_then(|ts| vcd.value(id, ts)) {
+                            let value = displayed_value(Some(&*settings), value);
+                            let text = settings.radix.format_value(&value);
+                            ui.output_mut(|output| output.copied_text = text);
+                        }
+                        ui.close_menu();
+                    }
+                    if ui.button("Copy Signal Name").clicked() {
+                        let text = signal_name(vcd, id);
+                        ui.output_mut(|output| output.copied_text = text);
+                        ui.close_menu();
+                    }
+                });
+
+                // Drag the name to reorder the signal
+                if response.drag_started() {
+                    self.dragging = Some(i);
+                }
+            }
+
+            // Pan with the middle button, or with the primary button while holding Alt
+            let visible = ui.clip_rect();
+            let (delta, pan) = ui.input(|i| {
+                let pointer = &i.pointer;
+                let button = pointer.middle_down() || (pointer.primary_down() && i.modifiers.alt);
+                let inside = pointer
+                    .press_origin()
+                    .is_some_and(|pos| visible.contains(pos));
+
+                (pointer.delta(), button && inside)
+            });
+            if pan {
+                ui.scroll_with_delta(delta);
+                ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
+            }
+
+            // Show where the dragged signal will be inserted, and move it on release
+            if let Some(source) = self.dragging {
+                let (pointer, released) =
+                    ui.input(|i| (i.pointer.interact_pos(), i.pointer.any_released()));
+                let target = pointer.map(|pos| {
+                    let row = ((pos.y - origin.y) / row_height).round().max(0.0) as usize;
+                    row.min(signals.len())
+                });
+
+                if let Some(target) = target {
+                    let y =
+                        origin.y + target as f32 * row_height - ui.spacing().item_spacing.y / 2.0;
+                    ui.painter().hline(
+                        origin.x + viewport.min.x..=origin.x + viewport.min.x + name_width,
+                        y,
+                        ui.visuals().selection.stroke,
+                    );
+                }
+
+                if released {
+                    self.dragging = None;
+                    if let Some(target) = target.filter(|_| source < self.displayed.len()) {
+                        let id = self.displayed.remove(source);
+                        let target = if target > source { target - 1 } else { target };
+                        self.displayed.insert(target, id);
+                    }
+                }
+            }
+        });
+
+        // Draw the timeline header, scrolled horizontally with the waveforms
+        let timeline = Timeline {
+            origin: output.inner_rect.min.x - output.state.offset.x + name_width,
+            start,
+            zoom,
+        };
+        self.view_time = start as f64 + (output.state.offset.x / zoom) as f64;
+
+        // Follow the pointer with a crosshair while it is over the waveforms
+        let waveform_area = Rect::from_min_max(
+            Pos2::new(
+                output.inner_rect.min.x + name_width,
+                output.inner_rect.min.y,
+            ),
+            output.inner_rect.max,
+        );
+        let crosshair = ui
+            .input(|i| i.pointer.hover_pos())
+            .filter(|_| ui.rect_contains_pointer(waveform_area))
+            .map(|pos| timeline.time(pos.x));
+
+        let flags: Vec<_> = [
+            (self.marker_a, MARKER_A_COLOR, Some("A")),
+            (self.marker_b, MARKER_B_COLOR, Some("B")),
+            (self.cursor, self.palette.cursor, None),
+            (crosshair, CROSSHAIR_COLOR, None),
+        ]
+        .into_iter()
+        .filter_map(|(time, color, name)| {
+            let time = time?;
+            let label = name.map_or_else(|| time_label(time, timescale), str::to_string);
+
+            Some((time, label, color))
+        })
+        .collect();
+        // The header is left blank above the name column
+        let header_rect = Rect::from_x_y_ranges(
+            header_rect.min.x + name_width..=header_rect.max.x,
+            header_rect.y_range(),
+        );
+        draw_timeline(
+            ui,
+            header_rect,
+            timeline,
+            end,
+            timescale,
+            &flags,
+            &self.bookmarks,
+        );
+
+        // Name the bookmark under the pointer
+        let hovered = header_response.hover_pos().and_then(|pos| {
+            self.bookmarks
+                .iter()
+                .find(|(_, time)| (timeline.x(*time) - pos.x).abs() <= 4.0)
+        });
+        if let Some((name, _)) = hovered {
+            header_response.on_hover_text(name.as_str());
+        }
+
+        // Draw the markers, cursor and crosshair across the header and all rows
+        let clip_rect = Rect::from_min_max(
+            Pos2::new(output.inner_rect.min.x + name_width, header_rect.min.y),
+            output.inner_rect.max,
+        );
+        for (time, _, color) in &flags {
+            let x = timeline.x(*time);
+            ui.painter_at(clip_rect).line_segment(
+                [Pos2::new(x, clip_rect.min.y), Pos2::new(x, clip_rect.max.y)],
+                (1.0, *color),
+            );
+        }
+
+        // Draw the overview of the whole time range, which pans the view when dragged or clicked
+        if let Some((rect, response)) = overview {
+            let rect = Rect::from_x_y_ranges(rect.min.x + name_width..=rect.max.x, rect.y_range());
+            let duration = (end - start) as f64;
+            let view_width = output.inner_rect.width() - name_width;
+            let visible = (self.view_time, self.view_time + (view_width / zoom) as f64);
+            let color = self.palette.waveform;
+            draw_overview(ui, rect, timestamps, start, end, visible, color);
+
+            if response.dragged() {
+                self.animation = None;
+                let delta = response.drag_delta().x as f64 / rect.width() as f64 * duration;
+                let offset = output.state.offset.x + (delta * zoom as f64) as f32;
+                self.scroll_offset = Some(offset.max(0.0));
+                ui.ctx().request_repaint();
+            } else if response.clicked() {
+                if let Some(pos) = response.interact_pointer_pos() {
+                    let fraction = ((pos.x - rect.min.x) / rect.width()) as f64;
+                    let time = start + (fraction * duration) as i64;
+                    self.center_on = Some(time.clamp(start, end));
+                    ui.ctx().request_repaint();
+                }
+            }
+        }
+
+        // Move the cursor to the next or previous edge with the arrow keys
+        if focused && !ui.ctx().wants_keyboard_input() {
+            let (left, right, shift) = ui.input(|i| {
+                (
+                    i.key_pressed(Key::ArrowLeft),
+                    i.key_pressed(Key::ArrowRight),
+                    i.modifiers.shift,
+                )
+            });
+            if left || right {
+                self.edge_search = Some(EdgeSearch {
+                    forward: right,
+                    any_signal: shift,
+                });
+            }
+
+            // Place markers at the cursor, unless the keys are typed into the row search
+            let (a, b, escape) = ui.input(|i| {
+                let key = |key| !row_focus && i.key_pressed(key);
+                (key(Key::A), key(Key::B), key(Key::Escape))
+            });
+            if a && self.cursor.is_some() {
+                self.marker_a = self.cursor;
+            }
+            if b && self.cursor.is_some() {
+                self.marker_b = self.cursor;
+            }
+            if escape {
+                self.marker_a = None;
+                self.marker_b = None;
+            }
+
+            // Zoom to fit everything, or only the markers with Shift
+            let (fit, shift) = ui.input(|i| (i.key_pressed(Key::F), i.modifiers.shift));
+            if fit && !row_focus {
+                self.zoom_to = Some(if shift {
+                    ZoomTarget::Markers
+                } else {
+                    ZoomTarget::All
+                });
+                ui.ctx().request_repaint();
+            }
+        }
+        if let Some(search) = self.edge_search.take() {
+            let ids: Vec<_> = match (&self.selected, search.any_signal) {
+                (Some(id), false) => vec![id.clone()],
+                _ => self
+                    .displayed
+                    .iter()
+                    .filter_map(Displayed::signal)
+                    .map(str::to_string)
+                    .collect(),
+            };
+            let time = self
+                .cursor
+                .unwrap_or(if search.forward { start - 1 } else { end });
+
+            if let Some(time) = find_edge(vcd, index, &ids, time, search.forward) {
+                self.cursor = Some(time);
+
+                // Scroll the cursor into view
+                let x = timeline.x(time);
+                let column = output.inner_rect.min.x + name_width..=output.inner_rect.max.x;
+                if !column.contains(&x) {
+                    let available = output.inner_rect.width() - name_width;
+                    let offset = (time - start) as f32 * zoom - available / 2.0;
+                    self.scroll_offset = Some(offset.max(0.0));
+                }
+                ui.ctx().request_repaint();
+            }
+        }
+
+        // Zoom with CTRL + Mousewheel, anchored at the time under the pointer
+        let (zoom_delta, pointer) = ui.input(|i| (i.zoom_delta(), i.pointer.hover_pos()));
+        let pointer = pointer.filter(|pos| zoom_delta != 1.0 && output.inner_rect.contains(*pos));
+        if let Some(pointer) = pointer {
+            let pointer_x = pointer.x - output.inner_rect.min.x;
+            let time = (pointer_x + output.state.offset.x - name_width) / zoom;
+
+            self.animation = None;
+            self.zoom = (zoom * zoom_delta).clamp(MIN_ZOOM, MAX_ZOOM);
+            self.scroll_offset = Some((name_width + time * self.zoom - pointer_x).max(0.0));
+            ui.ctx().request_repaint();
+        }
+
+        // Bookmark the cursor
+        let bookmark = ui.input(|i| i.key_pressed(Key::M));
+        if focused && !row_focus && !ui.ctx().wants_keyboard_input() && bookmark {
+            self.add_bookmark();
+        }
+    }
 }
 
-impl Gui {
-    pub(crate) fn new() -> Self {
-        Self {
-            enabled: true,
-            about_open: false,
-            vcd: None,
-            file_dialog: None,
+impl Command {
+    /// Every command, in the order of the menus.
+    fn all() -> impl Iterator<Item = Self> {
+        let file = [
+            Self::Open,
+            Self::ConnectStream,
+            Self::FileInfo,
+            Self::ExportImage,
+            Self::ExportSvg,
+            Self::ExportCsv,
+            Self::SaveSignalSet,
+            Self::LoadSignalSet,
+            Self::Reload,
+            Self::Close,
+            Self::Quit,
+            Self::Preferences,
+        ];
+        let view = [
+            Self::FindValue,
+            Self::GoToTime,
+            Self::CursorValues,
+            Self::ZoomToFit,
+            Self::ZoomToSelection,
+            Self::ResetView,
+            Self::PreviousEdge,
+            Self::NextEdge,
+            Self::PreviousChange,
+            Self::NextChange,
+            Self::SetMarkerA,
+            Self::SetMarkerB,
+            Self::ClearMarkers,
+            Self::AddBookmark,
+        ];
+
+        file.into_iter()
+            .chain(Theme::ALL.into_iter().map(Self::Theme))
+            .chain(view)
+            .chain([Self::About])
+    }
+
+    /// Shortcut text shown next to the command, if it has one.
+    fn shortcut(self, ctx: &Context) -> Option<String> {
+        let text = match self {
+            Self::Open => return Some(ctx.format_shortcut(&OPEN_SHORTCUT)),
+            Self::Close => return Some(ctx.format_shortcut(&CLOSE_SHORTCUT)),
+            Self::Quit => return Some(ctx.format_shortcut(&QUIT_SHORTCUT)),
+            Self::FindValue => return Some(ctx.format_shortcut(&FIND_SHORTCUT)),
+            Self::GoToTime => return Some(ctx.format_shortcut(&GO_TO_SHORTCUT)),
+            Self::About => return Some(ctx.format_shortcut(&ABOUT_SHORTCUT)),
+            Self::Reload => "F5",
+            Self::ZoomToFit => "F",
+            Self::ZoomToSelection => "Shift+F",
+            Self::PreviousEdge => "Left",
+            Self::NextEdge => "Right",
+            Self::PreviousChange => "Shift+Left",
+            Self::NextChange => "Shift+Right",
+            Self::SetMarkerA => "A",
+            Self::SetMarkerB => "B",
+            Self::ClearMarkers => "Esc",
+            Self::AddBookmark => "M",
+            _ => return None,
+        };
+
+        Some(text.to_string())
+    }
+}
+
+impl std::fmt::Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Open => "Open",
+            Self::ConnectStream => "Connect to Stream",
+            Self::FileInfo => "File Info",
+            Self::ExportImage => "Export Image",
+            Self::ExportSvg => "Export SVG",
+            Self::ExportCsv => "Export CSV",
+            Self::SaveSignalSet => "Save Signal Set",
+            Self::LoadSignalSet => "Load Signal Set",
+            Self::Reload => "Reload",
+            Self::Close => "Close",
+            Self::Quit => "Quit",
+            Self::Preferences => "Preferences",
+            Self::Theme(theme) => return write!(f, "Theme: {theme}"),
+            Self::FindValue => "Find Value",
+            Self::GoToTime => "Go to Time",
+            Self::CursorValues => "Cursor Values",
+            Self::ZoomToFit => "Zoom to Fit",
+            Self::ZoomToSelection => "Zoom to Selection",
+            Self::ResetView => "Reset View",
+            Self::PreviousEdge => "Previous Edge",
+            Self::NextEdge => "Next Edge",
+            Self::PreviousChange => "Previous Change",
+            Self::NextChange => "Next Change",
+            Self::SetMarkerA => "Set Marker A",
+            Self::SetMarkerB => "Set Marker B",
+            Self::ClearMarkers => "Clear Markers",
+            Self::AddBookmark => "Add Bookmark at Cursor",
+            Self::About => "About",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+impl ViewAnimation {
+    /// Zoom and left edge time at `t`, from 0 to 1 through the transition.
+    ///
+    /// The transition eases out, and zooms at a constant rate rather than a constant speed.
+    fn at(&self, t: f64) -> (f32, f64) {
+        if t >= 1.0 {
+            return self.to;
         }
+
+        let t = 1.0 - (1.0 - t.max(0.0)).powi(3);
+        let (from_zoom, from_time) = self.from;
+        let (to_zoom, to_time) = self.to;
+        let zoom = from_zoom * (to_zoom / from_zoom).powf(t as f32);
+
+        (zoom, from_time + (to_time - from_time) * t)
     }
+}
 
-    /// Create the UI using egui.
-    pub(crate) fn ui(&mut self, ctx: &Context, window: &Window) {
-        // Poll the file dialog
-        if let Some(handle) = self.file_dialog.as_ref() {
-            if handle.is_finished() {
-                if let Ok(vcd) = self.file_dialog.take().unwrap().join() {
-                    self.vcd = vcd.or(self.vcd.take());
-                }
-                self.enabled = true;
-            }
+impl Timeline {
+    /// Screen X coordinate for the given time.
+    fn x(&self, time: i64) -> f32 {
+        self.origin + (time - self.start) as f32 * self.zoom
+    }
+
+    /// Time at the given screen X coordinate.
+    fn time(&self, x: f32) -> i64 {
+        self.start + ((x - self.origin) / self.zoom).round() as i64
+    }
+}
+
+impl Displayed {
+    /// The signal ID, or `None` for a divider.
+    fn signal(&self) -> Option<&str> {
+        match self {
+            Self::Signal(id) => Some(id),
+            Self::Divider(_) => None,
         }
+    }
+}
 
-        // Draw the menu bar
-        egui::TopBottomPanel::top("menubar_container").show(ctx, |ui| {
-            ui.set_enabled(self.enabled);
-            egui::menu::bar(ui, |ui| {
-                ui.menu_button("File", |ui| {
-                    if ui.button("Open").clicked() {
-                        let dialog = AsyncFileDialog::new()
-                            .set_parent(window)
-                            .add_filter("Value Change Dump", &["vcd"]);
+impl Row {
+    fn depth(&self) -> usize {
+        match self {
+            Self::Scope { depth, .. } | Self::Signal { depth, .. } => *depth,
+        }
+    }
+}
 
-                        self.file_dialog = Some(std::thread::spawn(move || {
-                            pollster::block_on(dialog.pick_file())
-                                .and_then(|handle| std::fs::read(handle.path()).ok())
-                                .and_then(|buf| SignalDB::from_vcd(&buf[..]).ok())
-                        }));
-                        self.enabled = false;
+/// Describe a multisample anti-aliasing sample count.
+fn sample_count_label(sample_count: u32) -> String {
+    match sample_count {
+        1 => "Off".to_string(),
+        count => format!("{count}x MSAA"),
+    }
+}
 
-                        ui.close_menu();
-                    }
+/// Draw the density of value changes across the whole time range, with a box around the visible
+/// time range.
+fn draw_overview(
+    ui: &Ui,
+    rect: Rect,
+    timestamps: &[Timestamp],
+    start: i64,
+    end: i64,
+    visible: (f64, f64),
+    color: Color32,
+) {
+    let painter = ui.painter_at(rect);
+    let visuals = &ui.style().visuals;
+    painter.rect_filled(rect, 0.0, visuals.extreme_bg_color);
 
-                    if self.vcd.is_some() && ui.button("Close").clicked() {
-                        self.vcd = None;
-                        ui.close_menu();
-                    }
-                });
-                ui.menu_button("Help", |ui| {
-                    if ui.button("About...").clicked() {
-                        self.about_open = true;
-                        ui.close_menu();
-                    }
-                });
-            });
-        });
+    // One bar per pixel, on a log scale so that sparse activity is still visible
+    let columns = rect.width().max(1.0) as usize;
+    let duration = (end - start) as f64;
+    let time = |column: usize| start + (column as f64 / columns as f64 * duration) as i64;
+    let counts: Vec<_> = (0..columns)
+        .map(|column| {
+            let from = timestamps.partition_point(|ts| ts.get_value() < time(column));
+            let to = timestamps.partition_point(|ts| ts.get_value() < time(column + 1));
 
-        // Draw the main content area
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.set_enabled(self.enabled);
-            self.draw_vcd(ui);
-        });
+            to - from
+        })
+        .collect();
+    let max = counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+    for (column, count) in counts.into_iter().enumerate() {
+        if count == 0 {
+            continue;
+        }
+        let height = rect.height() * (count as f32).ln_1p() / max.ln_1p();
+        let x = rect.min.x + column as f32 + 0.5;
+        painter.line_segment(
+            [Pos2::new(x, rect.max.y), Pos2::new(x, rect.max.y - height)],
+            (1.0, color),
+        );
+    }
 
-        // Draw the windows (if requested by the user)
-        self.about_window(ctx);
+    // At least a few pixels wide, so the box can be found when zoomed in
+    let x = |time: f64| rect.min.x + ((time - start as f64) / duration) as f32 * rect.width();
+    let (left, right) = (x(visible.0), x(visible.1));
+    let center = (left + right) / 2.0;
+    let half_width = ((right - left) / 2.0).max(2.0);
+    let view = Rect::from_x_y_ranges(center - half_width..=center + half_width, rect.y_range());
+    painter.rect_filled(view, 0.0, visuals.selection.bg_fill.linear_multiply(0.4));
+    painter.rect_stroke(view, 0.0, visuals.selection.stroke);
+}
+
+/// Draw the timeline header with tick marks and time labels.
+///
+/// Flags for the cursor, markers and crosshair are labeled on top of the ticks, as
+/// `(time, label, color)`.
+fn draw_timeline(
+    ui: &Ui,
+    rect: Rect,
+    timeline: Timeline,
+    end: i64,
+    timescale: Option<Timescale>,
+    flags: &[(i64, String, Color32)],
+    bookmarks: &[(String, i64)],
+) {
+    let painter = ui.painter_at(rect);
+    let visuals = &ui.style().visuals;
+    let stroke = (1.0, visuals.text_color());
+    let font_id = egui::TextStyle::Small.resolve(ui.style());
+
+    painter.rect_filled(rect, 0.0, visuals.window_fill);
+    painter.line_segment([rect.left_bottom(), rect.right_bottom()], stroke);
+
+    let step = tick_spacing(timeline.zoom);
+    let first = timeline.time(rect.min.x).max(timeline.start);
+    let first = (first + step - 1).div_euclid(step) * step;
+    let last = timeline.time(rect.max.x).min(end);
+    let mut time = first;
+    while time <= last {
+        let x = timeline.x(time);
+        painter.line_segment(
+            [Pos2::new(x, rect.max.y - 4.0), Pos2::new(x, rect.max.y)],
+            stroke,
+        );
+        painter.text(
+            Pos2::new(x + 2.0, rect.center().y),
+            egui::Align2::LEFT_CENTER,
+            time_label(time, timescale),
+            font_id.clone(),
+            visuals.text_color(),
+        );
+        time += step;
     }
 
-    /// Show "About" window.
-    fn about_window(&mut self, ctx: &Context) {
-        egui::Window::new("About EdgeScan")
-            .open(&mut self.about_open)
-            .enabled(self.enabled)
-            .collapsible(false)
-            .default_pos((175.0, 175.0))
-            .fixed_size((350.0, 100.0))
-            .show(ctx, |ui| {
-                ui.add_space(5.0);
-                ui.label(concat!("EdgeScan version ", env!("CARGO_PKG_VERSION")));
-                ui.add_space(10.0);
-                ui.label(env!("CARGO_PKG_DESCRIPTION"));
-                ui.label(concat!("By: ", env!("CARGO_PKG_AUTHORS")));
-                ui.add_space(10.0);
-                ui.label("Made with 💖 in San Francisco!");
-                ui.add_space(10.0);
-                ui.horizontal(|ui| {
-                    ui.label("Website:");
-                    ui.hyperlink(env!("CARGO_PKG_HOMEPAGE"));
-                });
-            });
+    // Draw a small triangle pointing down at each bookmark
+    for (_, time) in bookmarks {
+        let x = timeline.x(*time);
+        let points = vec![
+            Pos2::new(x - 4.0, rect.max.y - 8.0),
+            Pos2::new(x + 4.0, rect.max.y - 8.0),
+            Pos2::new(x, rect.max.y),
+        ];
+        painter.add(egui::Shape::convex_polygon(
+            points,
+            BOOKMARK_COLOR,
+            egui::Stroke::NONE,
+        ));
     }
 
-    /// Draw the VCD waveforms.
-    fn draw_vcd(&self, ui: &mut Ui) {
-        let vcd = match self.vcd.as_ref() {
-            Some(vcd) => vcd,
-            None => return,
-        };
+    // Label the markers and cursor
+    for (time, label, color) in flags {
+        let galley = painter.layout_no_wrap(label.clone(), font_id.clone(), Color32::BLACK);
+        let label_rect = Rect::from_min_size(
+            Pos2::new(timeline.x(*time), rect.min.y),
+            galley.size() + Vec2::new(4.0, 0.0),
+        );
+        painter.rect_filled(label_rect, 2.0, *color);
+        painter.galley(label_rect.min + Vec2::new(2.0, 0.0), galley);
+    }
+}
 
-        let signals: Vec<_> = vcd
-            .get_signal_ids()
-            .into_iter()
-            .map(|id| (vcd.get_signal_fullname(&id).unwrap(), id))
-            .collect();
+/// Choose a "nice" tick spacing (1, 2, or 5 × 10ⁿ time units) for the zoom level.
+fn tick_spacing(zoom: f32) -> i64 {
+    let min_step = (MIN_TICK_SPACING / zoom) as f64;
+    let magnitude = 10.0_f64.powf(min_step.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|factor| factor * magnitude)
+        .find(|&step| step >= min_step)
+        .unwrap_or(min_step);
 
-        let sense = egui::Sense::hover();
-        let size = get_max_string_size(ui, signals.iter().map(|(name, _)| name));
+    (step.ceil() as i64).max(1)
+}
 
-        egui::ScrollArea::both()
-            .auto_shrink([false, false])
-            // TODO: use `show_viewport` and manually clip the samples drawn
-            .show(ui, |ui| {
-                for (i, (name, id)) in signals.iter().enumerate() {
-                    ui.horizontal(|ui| {
-                        // Allocate space for the fixed signal name column
-                        let (mut rect, _) = ui.allocate_exact_size(size, sense);
-                        let spacing_x = ui.spacing().item_spacing.x;
-
-                        let bg_color = ui.style().visuals.window_fill;
-                        let highlight_color = egui::Color32::from_additive_luminance(15);
-
-                        // Draw background for waveform column
-                        // TODO: Only draw the odd rows
-                        // Should also draw the full row all the way across all columns.
-                        let rect_bg = Rect::from_min_max(
-                            rect.right_top(),
-                            Pos2::new(ui.available_width(), rect.max.y),
-                        );
-                        let painter = ui.painter();
-                        painter.rect_filled(rect_bg.expand(3.0), 0.0, bg_color);
-                        if i % 2 != 0 {
-                            painter.rect_filled(rect_bg.expand(3.0), 0.0, highlight_color);
-                        }
+/// Format a time in the given unit, or as a count of ticks if the unit is unknown.
+fn time_label(time: i64, timescale: Option<Timescale>) -> String {
+    timescale.map_or_else(
+        || format!("{time} ticks"),
+        |timescale| format_time(time, timescale),
+    )
+}
 
-                        // Draw waveform
-                        // TODO: Draw a timeline header
-                        // TODO: Clip to window
-                        let zoom = 5.0; // TODO: Zoom with CTRL + Mousewheel
-                        let sample_size = Vec2::new(zoom, size.y);
-                        for ts in vcd.get_timestamps() {
-                            let (mut rect, _) = ui.allocate_exact_size(sample_size, sense);
-                            rect.set_width(zoom + spacing_x);
-                            draw_waveform_sample(ui.painter(), rect, vcd.value_at(id, ts).unwrap());
-                        }
+/// Format a frequency with an SI prefix.
+fn format_frequency(hz: f64) -> String {
+    let (value, unit) = [(1e12, "THz"), (1e9, "GHz"), (1e6, "MHz"), (1e3, "kHz")]
+        .into_iter()
+        .find(|&(factor, _)| hz >= factor)
+        .map_or((hz, "Hz"), |(factor, unit)| (hz / factor, unit));
 
-                        // Draw background for signal name column
-                        // TODO: Only draw the odd rows
-                        // Needs clipping on the waveform to avoid overdraw.
-                        let painter = ui.painter();
-                        rect.min.x = 0.0;
-                        rect.max.x = spacing_x + size.x;
-                        painter.rect_filled(rect.expand(3.0), 0.0, bg_color);
-                        if i % 2 != 0 {
-                            painter.rect_filled(rect.expand(3.0), 0.0, highlight_color);
-                        }
-
-                        // Draw signal name with fixed X position and width
-                        let text_galley = ui.fonts(|fonts| {
-                            egui::WidgetText::from(name)
-                                .into_text_job(
-                                    ui.style(),
-                                    egui::FontSelection::Default,
-                                    egui::Align::LEFT,
-                                )
-                                .into_galley(fonts)
-                        });
-                        rect.min.x = spacing_x;
-                        painter.galley_with_color(
-                            rect.min,
-                            text_galley.galley,
-                            ui.style().visuals.text_color(),
-                        );
-                    });
-                }
-            });
+    format!("{value:.3} {unit}")
+}
+
+/// Ask whether to load a file larger than `size` MiB, and whether to load only its first
+/// `timestamps` timestamps.
+///
+/// Returns the timestamp limit to load the file with, or `None` if the user cancelled. Files that
+/// cannot be measured, like standard input, are loaded without asking.
+fn confirm_large_file(path: &Path, size: u64, timestamps: u64) -> Option<Option<u64>> {
+    const MIB: u64 = 1024 * 1024;
+
+    let len = std::fs::metadata(path).map_or(0, |metadata| metadata.len());
+    if size == 0 || len <= size.saturating_mul(MIB) {
+        return Some(None);
+    }
+
+    let name = path
+        .file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy();
+    let len = if len >= 1024 * MIB {
+        format!("{:.1} GiB", len as f64 / (1024 * MIB) as f64)
+    } else {
+        format!("{:.1} MiB", len as f64 / MIB as f64)
+    };
+    let confirm = AsyncMessageDialog::new()
+        .set_title("Large File")
+        .set_description(&format!(
+            "`{name}` is {len}. Loading it may take a long time and use a lot of memory.\n\n\
+            Continue?",
+        ))
+        .set_level(MessageLevel::Warning)
+        .set_buttons(MessageButtons::YesNo);
+    if !pollster::block_on(confirm.show()) {
+        return None;
+    }
+    if timestamps == 0 {
+        return Some(None);
     }
+
+    let truncate = AsyncMessageDialog::new()
+        .set_title("Large File")
+        .set_description(&format!(
+            "Load only the first {timestamps} timestamps?\n\n\
+            Choose \"No\" to load the whole file.",
+        ))
+        .set_level(MessageLevel::Info)
+        .set_buttons(MessageButtons::YesNo);
+
+    Some(pollster::block_on(truncate.show()).then_some(timestamps))
+}
+
+/// Get the full name of a signal, falling back to its ID if the source has no name for it.
+fn signal_name(vcd: &dyn WaveformSource, id: &str) -> String {
+    vcd.signal_name(id).unwrap_or_else(|| id.to_string())
+}
+
+/// Draw a message centered in the available space.
+fn draw_message(ui: &mut Ui, text: &str) {
+    ui.centered_and_justified(|ui| {
+        ui.label(text);
+    });
+}
+
+/// Quote a CSV field if it contains a separator, quote, or line break.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Draw a progress bar for a file that is loading.
+fn draw_progress(ui: &mut Ui, read: u64, total: u64) {
+    const MIB: f64 = 1024.0 * 1024.0;
+
+    ui.vertical_centered(|ui| {
+        ui.add_space(ui.available_height() / 2.0 - 20.0);
+        ui.label(format!(
+            "Loading... {:.1} / {:.1} MiB",
+            read as f64 / MIB,
+            total as f64 / MIB,
+        ));
+        let fraction = if total > 0 {
+            read as f32 / total as f32
+        } else {
+            0.0
+        };
+        ui.add(egui::ProgressBar::new(fraction).show_percentage());
+    });
+}
+
+/// Log an error and report it to the user with a message box.
+pub fn show_error<E: std::error::Error + 'static>(err: E) {
+    error!("{err}");
+    let mut description = err.to_string();
+    for source in err.sources().skip(1) {
+        error!("  Caused by: {source}");
+        description.push_str(&format!("\n\nCaused by: {source}"));
+    }
+
+    MessageDialog::new()
+        .set_title("Error")
+        .set_description(&description)
+        .set_level(MessageLevel::Error)
+        .set_buttons(MessageButtons::Ok)
+        .show();
+}
+
+/// Report the signals of a signal set that could not be found.
+fn show_missing_signals(missing: &[String]) {
+    const MAX_LISTED: usize = 20;
+
+    let mut description = format!(
+        "{} signals in the signal set are not in this file:\n",
+        missing.len()
+    );
+    for name in missing.iter().take(MAX_LISTED) {
+        description.push_str(&format!("\n{name}"));
+    }
+    if missing.len() > MAX_LISTED {
+        description.push_str(&format!("\n...and {} more", missing.len() - MAX_LISTED));
+    }
+    warn!("{description}");
+
+    MessageDialog::new()
+        .set_title("Missing Signals")
+        .set_description(&description)
+        .set_level(MessageLevel::Warning)
+        .set_buttons(MessageButtons::Ok)
+        .show();
 }
 
 fn get_max_string_size<'a>(ui: &Ui, strings: impl Iterator<Item = &'a String>) -> Vec2 {
@@ -206,36 +4278,510 @@ fn get_max_string_size<'a>(ui: &Ui, strings: impl Iterator<Item = &'a String>) -
     })
 }
 
-fn draw_waveform_sample(painter: &Painter, rect: Rect, sample: SignalValue) {
-    let stroke = (1.0, egui::Color32::GREEN);
+/// Match a signal name against a filter.
+///
+/// The filter is a substring, or a glob pattern matching the full name if it contains `*` or `?`.
+/// An empty filter matches everything.
+fn matches_filter(name: &str, filter: &str, case_sensitive: bool) -> bool {
+    let (name, filter) = if case_sensitive {
+        (name.to_string(), filter.to_string())
+    } else {
+        (name.to_lowercase(), filter.to_lowercase())
+    };
+
+    if filter.contains(['*', '?']) {
+        let pattern: Vec<_> = filter.chars().collect();
+        let name: Vec<_> = name.chars().collect();
+
+        glob_match(&pattern, &name)
+    } else {
+        name.contains(&filter)
+    }
+}
+
+/// Match text against a glob pattern, where `*` matches any run of characters and `?` matches a
+/// single character.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+        Some(('?', rest)) => !text.is_empty() && glob_match(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && glob_match(rest, &text[1..]),
+    }
+}
+
+/// Every scope that contains a signal, directly or below, as sorted dotted paths.
+fn signal_scopes<'a>(names: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut scopes: Vec<String> = names
+        .flat_map(|name| {
+            name.match_indices('.')
+                .map(|(index, _)| name[..index].to_string())
+        })
+        .collect();
+    scopes.sort_unstable();
+    scopes.dedup();
+
+    scopes
+}
+
+/// Check whether a signal's full name is below a dotted scope path.
+fn in_scope(name: &str, scope: &str) -> bool {
+    name.strip_prefix(scope)
+        .is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// Arrange signals into a tree of rows grouped by their dotted scope paths.
+///
+/// Each signal is given as `(full name, id)`. Rows below a collapsed scope are omitted.
+fn signal_rows(
+    signals: impl Iterator<Item = (String, String)>,
+    collapsed: &HashSet<String>,
+) -> Vec<Row> {
+    let mut signals: Vec<_> = signals.collect();
+    signals.sort_unstable();
+
+    let mut rows = Vec::new();
+    let mut scopes: Vec<&str> = Vec::new();
+    let mut collapsed_depth = None;
+    for (name, id) in &signals {
+        let mut parts: Vec<_> = name.split('.').collect();
+        let leaf = parts.pop().unwrap_or_default();
+
+        // Close scopes that this signal is not part of
+        let common = scopes
+            .iter()
+            .zip(&parts)
+            .take_while(|(a, b)| a == b)
+            .count();
+        scopes.truncate(common);
+        if collapsed_depth.is_some_and(|depth| depth >= common) {
+            collapsed_depth = None;
+        }
+
+        // Open new scopes
+        for (depth, part) in parts.iter().enumerate().skip(common) {
+            scopes.push(*part);
+            if collapsed_depth.is_some() {
+                continue;
+            }
+
+            let path = scopes.join(".");
+            let is_collapsed = collapsed.contains(&path);
+            let arrow = if is_collapsed { "▶" } else { "▼" };
+            if is_collapsed {
+                collapsed_depth = Some(depth);
+            }
+            rows.push(Row::Scope {
+                path,
+                label: format!("{arrow} {part}"),
+                depth,
+            });
+        }
+
+        if collapsed_depth.is_none() {
+            rows.push(Row::Signal {
+                id: id.clone(),
+                label: leaf.to_string(),
+                depth: scopes.len(),
+            });
+        }
+    }
+
+    rows
+}
+
+/// Find the sample holding the value at the given time.
+fn sample_at(timestamps: &[Timestamp], time: i64) -> Option<Timestamp> {
+    let index = timestamps.partition_point(|ts| ts.get_value() <= time);
+
+    index.checked_sub(1).map(|index| timestamps[index])
+}
+
+/// Find the timestamp nearest to the given time.
+fn nearest_timestamp(timestamps: &[Timestamp], time: i64) -> Option<i64> {
+    let index = timestamps.partition_point(|ts| ts.get_value() < time);
+    let before = index.checked_sub(1).and_then(|index| timestamps.get(index));
+    let after = timestamps.get(index);
+
+    [before, after]
+        .into_iter()
+        .flatten()
+        .map(|ts| ts.get_value())
+        .min_by_key(|ts| (ts - time).abs())
+}
+
+/// Find the edge of a signal nearest to `time`, within [`SNAP_DISTANCE`] pixels at the given zoom.
+fn nearest_edge(
+    vcd: &dyn WaveformSource,
+    index: &Index,
+    id: &str,
+    time: i64,
+    zoom: f32,
+) -> Option<i64> {
+    let distance = (SNAP_DISTANCE / zoom) as i64;
+    let ids = [id.to_string()];
+
+    // An edge exactly at `time` is found searching backward from just after it
+    [
+        find_edge(vcd, index, &ids, time + 1, false),
+        find_edge(vcd, index, &ids, time, true),
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|edge| (edge - time).abs() <= distance)
+    .min_by_key(|edge| (edge - time).abs())
+}
+
+/// Find the next or previous time at which any of the given signals changes value, relative to
+/// `time`.
+///
+/// Each signal's cached transitions are binary searched, so only the edges found are visited.
+fn find_edge(
+    vcd: &dyn WaveformSource,
+    index: &Index,
+    ids: &[String],
+    time: i64,
+    forward: bool,
+) -> Option<i64> {
+    let timestamps = index.timestamps();
+    let time_at = |position: &usize| timestamps[*position].get_value();
+    let edges = ids.iter().filter_map(|id| {
+        // The first transition is the start of the dump, not an edge
+        let transitions = index.transitions(vcd, id);
+        let edges = transitions.get(1..).unwrap_or_default();
+        if forward {
+            let next = edges.partition_point(|position| time_at(position) <= time);
+            edges.get(next).map(time_at)
+        } else {
+            let previous = edges.partition_point(|position| time_at(position) < time);
+            previous
+                .checked_sub(1)
+                .map(|previous| time_at(&edges[previous]))
+        }
+    });
+
+    if forward {
+        edges.min()
+    } else {
+        edges.max()
+    }
+}
+
+/// A value of a signal in the bit order it is displayed in.
+fn displayed_value(settings: Option<&SignalSettings>, value: SignalValue) -> SignalValue {
+    match settings {
+        Some(settings) if settings.reverse_bits => reverse_bits(value),
+        _ => value,
+    }
+}
+
+/// Find the next or previous time, relative to `time`, at which a signal starts matching a pattern.
+///
+/// Values are matched with their bits reversed if `reverse` is set. A value only changes at the
+/// signal's cached transitions, so only they are visited.
+fn find_value(
+    vcd: &dyn WaveformSource,
+    index: &Index,
+    id: &str,
+    time: i64,
+    forward: bool,
+    reverse: bool,
+    pattern: &Pattern,
+) -> Option<i64> {
+    let timestamps = index.timestamps();
+    let transitions = index.transitions(vcd, id);
+    let time_at = |position: usize| timestamps[transitions[position]].get_value();
+    let matches = |position: usize| {
+        let value = vcd.value(id, timestamps[transitions[position]]);
+        value.is_some_and(|value| {
+            pattern.matches(&if reverse { reverse_bits(value) } else { value })
+        })
+    };
+    let starts = |&position: &usize| matches(position) && (position == 0 || !matches(position - 1));
+
+    let position = if forward {
+        let first = transitions.partition_point(|&index| timestamps[index].get_value() <= time);
+        (first..transitions.len()).find(starts)
+    } else {
+        let last = transitions.partition_point(|&index| timestamps[index].get_value() < time);
+        (0..last).rev().find(starts)
+    };
+
+    position.map(time_at)
+}
+
+/// Draw a single-bit signal as polylines, including the vertical edges between levels.
+///
+/// High-impedance runs are drawn at the middle level in their own color, and unknown values are
+/// drawn as filled bands that break the line.
+fn draw_bit_waveform(painter: &Painter, samples: &[(Rect, SignalValue)], style: WaveformStyle) {
+    let mut line: Vec<Pos2> = Vec::new();
+    let mut line_color = style.color;
+
+    for (rect, value) in samples {
+        let bit = match value {
+            SignalValue::Literal(bits, _) => bits.first(),
+            SignalValue::Symbol(_) => None,
+        };
+        let level = match bit {
+            Some(BitValue::Low) => Some((rect.max.y, style.color)),
+            Some(BitValue::High) => Some((rect.min.y, style.color)),
+            Some(BitValue::HighZ) => Some((rect.center().y, style.highz)),
+            _ => None,
+        };
+
+        match level {
+            Some((y, level_color)) => {
+                // The edge into a new color is drawn in the old color
+                let start = Pos2::new(rect.min.x, y);
+                if !line.is_empty() {
+                    line.push(start);
+                }
+                if level_color != line_color {
+                    flush_line(painter, &mut line, (style.width, line_color));
+                    line_color = level_color;
+                }
+                if line.is_empty() {
+                    line.push(start);
+                }
+                line.push(Pos2::new(rect.max.x, y));
+            }
+            None => {
+                flush_line(painter, &mut line, (style.width, line_color));
+                painter.rect_filled(*rect, 0.0, style.unknown.linear_multiply(0.5));
+            }
+        }
+    }
+
+    flush_line(painter, &mut line, (style.width, line_color));
+}
+
+/// Draw the numeric values of a signal as heights in the row.
+///
+/// `range` is the values at the bottom and top of the row, or `None` to fit the values. Values
+/// that are not numbers break the line, and are drawn as filled bands.
+fn draw_analog_waveform(
+    painter: &Painter,
+    samples: &[(Rect, SignalValue)],
+    radix: Radix,
+    plot: Plot,
+    range: Option<(f64, f64)>,
+    style: WaveformStyle,
+) {
+    let stroke = (style.width, style.color);
+    let values: Vec<_> = samples
+        .iter()
+        .map(|(_, value)| radix.to_f64(value))
+        .collect();
+    let (min, max) = range.unwrap_or_else(|| {
+        values
+            .iter()
+            .flatten()
+            .fold((f64::MAX, f64::MIN), |(min, max), &value| {
+                (min.min(value), max.max(value))
+            })
+    });
+    // A constant value is drawn in the middle of the row
+    let (min, max) = if max > min {
+        (min, max)
+    } else {
+        (min - 1.0, min + 1.0)
+    };
+
+    let mut line: Vec<Pos2> = Vec::new();
+    for ((rect, _), value) in samples.iter().zip(values) {
+        let value = match value {
+            Some(value) => value.clamp(min, max),
+            None => {
+                flush_line(painter, &mut line, stroke);
+                painter.rect_filled(*rect, 0.0, style.unknown.linear_multiply(0.5));
+                continue;
+            }
+        };
+        let y = rect.max.y - ((value - min) / (max - min)) as f32 * rect.height();
+
+        line.push(Pos2::new(rect.min.x, y));
+        if plot == Plot::Step {
+            line.push(Pos2::new(rect.max.x, y));
+        }
+    }
+
+    // The last value holds until the end of its segment
+    if let (Some(last), Some((rect, _))) = (line.last().copied(), samples.last()) {
+        if plot == Plot::Line {
+            line.push(Pos2::new(rect.max.x, last.y));
+        }
+    }
+    flush_line(painter, &mut line, stroke);
+}
+
+/// Draw the formatted values of a signal as text, with a tick at each transition.
+///
+/// Values that do not fit between their transitions are left out.
+fn draw_value_text(
+    painter: &Painter,
+    samples: &[(Rect, SignalValue)],
+    radix: Radix,
+    style: WaveformStyle,
+) {
+    let font_style = painter.ctx().style();
+    for (i, (rect, value)) in samples.iter().enumerate() {
+        let color = match value {
+            SignalValue::Literal(bits, _)
+                if bits.iter().all(|bit| matches!(bit, BitValue::HighZ)) =>
+            {
+                style.highz
+            }
+            SignalValue::Literal(bits, _)
+                if bits
+                    .iter()
+                    .any(|bit| !matches!(bit, BitValue::Low | BitValue::High)) =>
+            {
+                style.unknown
+            }
+            _ => style.color,
+        };
+
+        // The first sample starts at or before the left edge, not at a transition
+        if i > 0 {
+            let tick = rect.height() / 4.0;
+            painter.line_segment(
+                [
+                    rect.left_top() + Vec2::new(0.0, tick),
+                    rect.left_bottom() - Vec2::new(0.0, tick),
+                ],
+                (style.width, style.color),
+            );
+        }
+
+        let text = radix.format_value(value);
+        let mut galley = painter.layout_no_wrap(
+            text.clone(),
+            egui::TextStyle::Monospace.resolve(&font_style),
+            color,
+        );
+        if galley.size().y > rect.height() {
+            // Compact rows use small text
+            let font_id = egui::TextStyle::Small.resolve(&font_style);
+            galley = painter.layout_no_wrap(text, font_id, color);
+        }
+        if galley.size().x + style.width * 4.0 <= rect.width() {
+            painter.galley(rect.center() - galley.size() / 2.0, galley);
+        }
+    }
+}
+
+/// Fill a rectangle with diagonal lines.
+///
+/// The lines are aligned to the screen, so they stay in place while the rectangle scrolls.
+fn draw_hatched(painter: &Painter, rect: Rect, color: Color32) {
+    if rect.width() <= 0.0 || rect.height() <= 0.0 {
+        return;
+    }
+
+    let painter = painter.with_clip_rect(rect.intersect(painter.clip_rect()));
+    let mut x = ((rect.min.x - rect.height()) / HATCH_SPACING).floor() * HATCH_SPACING;
+    while x < rect.max.x {
+        let bottom = Pos2::new(x, rect.max.y);
+        let top = Pos2::new(x + rect.height(), rect.min.y);
+        painter.line_segment([bottom, top], (1.0, color));
+        x += HATCH_SPACING;
+    }
+}
+
+/// Draw the points as a single line shape and clear them.
+fn flush_line(painter: &Painter, line: &mut Vec<Pos2>, stroke: (f32, Color32)) {
+    if line.len() > 1 {
+        painter.add(egui::Shape::line(std::mem::take(line), stroke));
+    }
+    line.clear();
+}
+
+/// Draw a multi-bit value.
+fn draw_waveform_sample(
+    painter: &Painter,
+    rect: Rect,
+    sample: &SignalValue,
+    radix: Radix,
+    style: WaveformStyle,
+) {
+    let stroke = (style.width, style.color);
 
     match sample {
         SignalValue::Literal(bits, _) => {
-            if bits.len() == 1 {
-                match bits[0] {
-                    // TODO: Use paths instead of line segments?
-                    BitValue::Low => {
-                        painter.line_segment([rect.left_bottom(), rect.right_bottom()], stroke);
-                    }
-                    BitValue::High => {
-                        painter.line_segment([rect.left_top(), rect.right_top()], stroke);
-                    }
-                    BitValue::HighZ => {
-                        // TODO
-                        painter.line_segment([rect.left_top(), rect.right_top()], stroke);
-                        painter.line_segment([rect.left_bottom(), rect.right_bottom()], stroke);
-                    }
-                    _ => {
-                        // TODO
-                        painter.rect_filled(rect, 0.0, egui::Color32::RED);
-                    }
-                }
+            if bits.iter().all(|bit| matches!(bit, BitValue::HighZ)) {
+                // A floating bus
+                painter.line_segment(
+                    [rect.left_center(), rect.right_center()],
+                    (stroke.0, style.highz),
+                );
+            } else if bits
+                .iter()
+                .any(|bit| !matches!(bit, BitValue::Low | BitValue::High | BitValue::HighZ))
+            {
+                // Any unknown bit makes the whole value invalid
+                let fill = style.unknown.linear_multiply(0.5);
+                draw_bus_segment(
+                    painter,
+                    rect,
+                    radix.format(bits),
+                    (stroke.0, style.unknown),
+                    fill,
+                );
+            } else if bits.iter().any(|bit| matches!(bit, BitValue::HighZ)) {
+                // Partially driven bus
+                let stroke = (stroke.0, style.highz);
+                draw_bus_segment(
+                    painter,
+                    rect,
+                    radix.format(bits),
+                    stroke,
+                    Color32::TRANSPARENT,
+                );
             } else {
-                // TODO
-                painter.line_segment([rect.left_top(), rect.right_top()], stroke);
-                painter.line_segment([rect.left_bottom(), rect.right_bottom()], stroke);
+                draw_bus_segment(
+                    painter,
+                    rect,
+                    radix.format(bits),
+                    stroke,
+                    Color32::TRANSPARENT,
+                );
             }
         }
         SignalValue::Symbol(_) => (),
     }
 }
+
+/// Draw a multi-bit value as a hexagon with angled transitions at both ends.
+///
+/// The value is drawn centered in the segment when there is enough room for it.
+fn draw_bus_segment(
+    painter: &Painter,
+    rect: Rect,
+    text: String,
+    stroke: (f32, Color32),
+    fill: Color32,
+) {
+    let slant = Vec2::new((rect.height() / 4.0).min(rect.width() / 2.0), 0.0);
+    let points = vec![
+        rect.left_center(),
+        rect.left_top() + slant,
+        rect.right_top() - slant,
+        rect.right_center(),
+        rect.right_bottom() - slant,
+        rect.left_bottom() + slant,
+    ];
+    painter.add(egui::Shape::convex_polygon(points, fill, stroke));
+
+    let style = painter.ctx().style();
+    let font_id = egui::TextStyle::Monospace.resolve(&style);
+    let mut galley = painter.layout_no_wrap(text.clone(), font_id, stroke.1);
+    if galley.size().y > rect.height() {
+        // Compact rows use small text
+        let font_id = egui::TextStyle::Small.resolve(&style);
+        galley = painter.layout_no_wrap(text, font_id, stroke.1);
+    }
+    if galley.size().x + slant.x * 4.0 <= rect.width() {
+        painter.galley(rect.center() - galley.size() / 2.0, galley);
+    }
+}
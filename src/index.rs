@@ -0,0 +1,179 @@
+//! Cached access to the value changes in a [`WaveformSource`], for drawing large dumps.
+//!
+//! [`WaveformSource::timestamps`] builds a new list on every call, and finding the changes of a
+//! signal means querying its value at every timestamp. [`Index`] keeps the timestamps, and the
+//! positions where each signal changes, so that only the visible part of a dump is visited when
+//! drawing a frame.
+
+use crate::waveform::WaveformSource;
+use dwfv::signaldb::{SignalValue, Timestamp};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Timestamps of a [`WaveformSource`], and the value changes of each signal.
+///
+/// The index must be rebuilt when the source is replaced.
+#[derive(Debug, Default)]
+pub struct Index {
+    timestamps: Vec<Timestamp>,
+
+    // Positions in `timestamps` where each signal changes value, keyed by signal ID. Built the
+    // first time a signal is drawn.
+    transitions: RefCell<HashMap<String, Rc<[usize]>>>,
+}
+
+impl Index {
+    pub fn new(db: &dyn WaveformSource) -> Self {
+        Self {
+            timestamps: db.timestamps(),
+            transitions: RefCell::default(),
+        }
+    }
+
+    /// All timestamps, in order.
+    pub fn timestamps(&self) -> &[Timestamp] {
+        &self.timestamps
+    }
+
+    /// Positions in [`Index::timestamps`] where the signal changes value.
+    ///
+    /// The first timestamp is always included.
+    pub fn transitions(&self, db: &dyn WaveformSource, id: &str) -> Rc<[usize]> {
+        if let Some(transitions) = self.transitions.borrow().get(id) {
+            return transitions.clone();
+        }
+
+        let mut last = None;
+        let transitions: Rc<[usize]> = self
+            .timestamps
+            .iter()
+            .enumerate()
+            .filter_map(|(index, ts)| {
+                let value = db.value(id, *ts);
+                let changed = index == 0 || value != last;
+                last = value;
+
+                changed.then_some(index)
+            })
+            .collect();
+        self.transitions
+            .borrow_mut()
+            .insert(id.to_string(), transitions.clone());
+
+        transitions
+    }
+
+    /// The values of a signal between `from` and `to`, as `(start, end, value)` segments.
+    ///
+    /// The first segment holds the value at `from`, and may start before it. Each segment ends
+    /// where the next one starts; the last ends at the next change after `to`, or at `end`.
+    /// Values that can't be read from the source are skipped.
+    pub fn segments<'a>(
+        &'a self,
+        db: &'a dyn WaveformSource,
+        id: &'a str,
+        from: i64,
+        to: i64,
+        end: i64,
+    ) -> impl Iterator<Item = (i64, i64, SignalValue)> + 'a {
+        let transitions = self.transitions(db, id);
+        let time = |index: usize| self.timestamps[index].get_value();
+
+        let first = transitions
+            .partition_point(|&index| time(index) <= from)
+            .saturating_sub(1);
+        let last = transitions.partition_point(|&index| time(index) <= to);
+
+        (first..last.max(first)).filter_map(move |index| {
+            let ts = self.timestamps[transitions[index]];
+            let end = transitions.get(index + 1).map_or(end, |&next| time(next));
+
+            Some((ts.get_value(), end, db.value(id, ts)?))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::radix::Radix;
+    use dwfv::signaldb::SignalDB;
+
+    /// A clock that changes at 0, 10, 20 and 30, and is written again unchanged at 25.
+    const CLOCK: &str = "#0\n0!\n#10\n1!\n#20\n0!\n#25\n0!\n#30\n1!\n";
+
+    /// The database and index of a dump with a single signal, and the ID of that signal.
+    fn index(changes: &str) -> (SignalDB, Index, String) {
+        let vcd = format!(
+            "$timescale 1 ns $end\n\
+             $scope module top $end\n\
+             $var wire 1 ! clk $end\n\
+             $upscope $end\n\
+             $enddefinitions $end\n\
+             {changes}"
+        );
+        let db = SignalDB::from_vcd(vcd.as_bytes()).unwrap();
+        let index = Index::new(&db);
+        let id = db.get_signal_ids().remove(0);
+
+        (db, index, id)
+    }
+
+    /// The segments between `from` and `to`, with the last sample held for one time unit.
+    fn segments(changes: &str, from: i64, to: i64) -> Vec<(i64, i64, String)> {
+        let (db, index, id) = index(changes);
+        let end = index.timestamps().last().map_or(0, |ts| ts.get_value()) + 1;
+
+        index
+            .segments(&db, &id, from, to, end)
+            .map(|(from, to, value)| (from, to, Radix::Binary.format_value(&value)))
+            .collect()
+    }
+
+    #[test]
+    fn segments_in_range() {
+        let ranges = [
+            // Inside a single value
+            (12, 18, vec![(10, 20, "1")]),
+            // Every value, the unchanged write at 25 included in its segment
+            (
+                0,
+                30,
+                vec![(0, 10, "0"), (10, 20, "1"), (20, 30, "0"), (30, 31, "1")],
+            ),
+            // Before the first transition
+            (-10, -5, vec![]),
+            // Past the last transition, which is held until the end
+            (40, 50, vec![(30, 31, "1")]),
+            // A range ending just before a transition doesn't include it
+            (20, 29, vec![(20, 30, "0")]),
+            (20, 30, vec![(20, 30, "0"), (30, 31, "1")]),
+        ];
+
+        for (from, to, expected) in ranges {
+            let expected: Vec<_> = expected
+                .into_iter()
+                .map(|(from, to, value)| (from, to, value.to_string()))
+                .collect();
+            assert_eq!(segments(CLOCK, from, to), expected, "{from}..{to}");
+        }
+    }
+
+    #[test]
+    fn segments_of_constant_signal() {
+        let changes = "#0\n1!\n#50\n1!\n";
+
+        assert_eq!(segments(changes, 0, 100), [(0, 51, "1".to_string())]);
+        assert_eq!(segments(changes, 20, 30), [(0, 51, "1".to_string())]);
+    }
+
+    #[test]
+    fn segments_of_empty_dump() {
+        let (db, index, id) = index("");
+
+        assert!(index.timestamps().is_empty());
+        assert!(index.transitions(&db, &id).is_empty());
+        assert_eq!(index.segments(&db, &id, 0, 100, 0).count(), 0);
+    }
+}
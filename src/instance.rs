@@ -0,0 +1,152 @@
+//! Single-instance support: files opened while EdgeScan is running open in the running window.
+//!
+//! The running instance listens on a TCP port on the loopback interface, and writes the port to a
+//! file along with a random token. A new instance that is given a file connects to the port and
+//! sends the token and the path, instead of opening a window of its own. Other local programs can
+//! connect to the port too, but only those that can read the file, which is private to the user,
+//! can have files opened.
+
+use crate::framework::UserEvent;
+use log::{info, warn};
+use std::collections::hash_map::RandomState;
+use std::fmt::Write as _;
+use std::fs::OpenOptions;
+use std::hash::{BuildHasher as _, Hasher as _};
+use std::io::{BufRead as _, BufReader, Write as _};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use winit::event_loop::EventLoopProxy;
+
+/// Sent before the path, so that an unrelated program on a stale port is not mistaken for EdgeScan.
+const GREETING: &str = "EdgeScan open";
+
+/// Reply from the running instance after it has received the path.
+const ACK: &str = "ok";
+
+/// How long to wait for a running instance to respond.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Send a file to a running instance.
+///
+/// `port_file` is the file the running instance wrote its port to. Returns `false` if there is no
+/// running instance, or it did not accept the file.
+pub fn forward(port_file: &Path, path: &Path) -> bool {
+    // The running instance may have a different working directory
+    let path = match path.canonicalize() {
+        Ok(path) => path,
+        Err(_) => return false,
+    };
+    let ((port, token), path) = match (read_port(port_file), path.to_str()) {
+        (Some(port), Some(path)) => (port, path),
+        _ => return false,
+    };
+
+    let send = || -> std::io::Result<bool> {
+        let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+        let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        writeln!(stream, "{GREETING}\n{token}\n{path}")?;
+
+        let mut reply = String::new();
+        BufReader::new(stream).read_line(&mut reply)?;
+
+        Ok(reply.trim_end() == ACK)
+    };
+
+    match send() {
+        Ok(true) => {
+            info!("Opened `{path}` in the running instance");
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Listen for files sent by new instances, and send them to the event loop.
+///
+/// The port and the token are written to `port_file`, replacing those of any instance that is no
+/// longer running. Only the user can read the file.
+pub fn listen(port_file: &Path, proxy: EventLoopProxy<UserEvent>) -> std::io::Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let port = listener.local_addr()?.port();
+    let token = random_token();
+    if let Some(dir) = port_file.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    // The file is created again, so that it is private even if an older one was not
+    let _ = std::fs::remove_file(port_file);
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    writeln!(options.open(port_file)?, "{port}\n{token}")?;
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            match receive(stream, &token) {
+                Ok(Some(path)) => {
+                    // The event loop is gone when the window is closing
+                    if proxy.send_event(UserEvent::Open(path)).is_err() {
+                        break;
+                    }
+                }
+                Ok(None) => (),
+                Err(err) => warn!("Unable to receive a file from another instance: {err}"),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Read a path sent by another instance, and acknowledge it.
+///
+/// Returns `None` if the connection is not from EdgeScan, or did not send `token`.
+fn receive(stream: TcpStream, token: &str) -> std::io::Result<Option<PathBuf>> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+
+    let mut greeting = String::new();
+    reader.read_line(&mut greeting)?;
+    if greeting.trim_end() != GREETING {
+        return Ok(None);
+    }
+
+    let mut sent = String::new();
+    reader.read_line(&mut sent)?;
+    if sent.trim_end() != token {
+        warn!("Ignored a file sent without the token of this instance");
+        return Ok(None);
+    }
+
+    let mut path = String::new();
+    reader.read_line(&mut path)?;
+    writeln!(&stream, "{ACK}")?;
+
+    Ok(Some(PathBuf::from(path.trim_end_matches(['\r', '\n']))))
+}
+
+/// Read the port and the token of the running instance.
+fn read_port(port_file: &Path) -> Option<(u16, String)> {
+    let text = std::fs::read_to_string(port_file).ok()?;
+    let mut lines = text.lines();
+    let port = lines.next()?.trim().parse().ok()?;
+    let token = lines.next()?.trim().to_string();
+
+    Some((port, token))
+}
+
+/// Create a token that can't be guessed by programs that can't read the port file.
+fn random_token() -> String {
+    // The standard library seeds the keys of each `RandomState` from the operating system
+    let mut token = String::new();
+    for _ in 0..2 {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(std::process::id());
+        write!(token, "{:016x}", hasher.finish()).unwrap();
+    }
+
+    token
+}
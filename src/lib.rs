@@ -1,4 +1,20 @@
+#[cfg(feature = "gui")]
 pub mod config;
+#[cfg(feature = "gui")]
 pub mod framework;
+pub mod fst;
+#[cfg(feature = "gui")]
 pub mod gpu;
+#[cfg(feature = "gui")]
 pub mod gui;
+pub mod index;
+#[cfg(feature = "gui")]
+pub mod instance;
+pub mod radix;
+pub mod stream;
+#[cfg(feature = "gui")]
+pub mod svg;
+pub mod time;
+#[cfg(feature = "gui")]
+pub mod watch;
+pub mod waveform;
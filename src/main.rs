@@ -1,20 +1,28 @@
-use edgescan::{config::Config, framework::Framework, gpu::Gpu};
-use error_iter::ErrorIter as _;
-use log::error;
-use rfd::{MessageButtons, MessageDialog, MessageLevel};
-use std::{process::ExitCode, time::Duration};
+use edgescan::{
+    config::Config,
+    framework::{self, Framework, UserEvent},
+    gpu::Gpu,
+    gui::{show_error, STDIN_PATH},
+    instance,
+    waveform::Waveform,
+};
+use log::warn;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
 use thiserror::Error;
 use winit::{
-    dpi::LogicalSize,
-    event::Event,
-    event_loop::{ControlFlow, EventLoop},
-    window::{Window, WindowBuilder},
+    dpi::{LogicalSize, PhysicalPosition},
+    event::{Event, StartCause, VirtualKeyCode, WindowEvent},
+    event_loop::{ControlFlow, EventLoopBuilder},
+    window::{Fullscreen, Window, WindowBuilder},
 };
 use winit_input_helper::WinitInputHelper;
 
-#[cfg(target_os = "macos")]
-use std::time::Instant;
-
 #[derive(Debug, Error)]
 enum Error {
     #[error("Unable to create window")]
@@ -25,30 +33,205 @@ enum Error {
 
     #[error("Configuration error")]
     Config(#[from] edgescan::config::Error),
+
+    #[error(transparent)]
+    Load(#[from] edgescan::waveform::LoadError),
+
+    #[error("Invalid arguments: {0}")]
+    Args(String),
+
+    #[error("Unable to render image")]
+    Render(#[from] edgescan::gpu::CaptureError),
+}
+
+/// Options for rendering a file to an image without opening a window.
+struct RenderArgs {
+    output: PathBuf,
+    input: PathBuf,
+    width: u32,
+    height: u32,
+    from: Option<i64>,
+    to: Option<i64>,
+}
+
+/// Parse the arguments for headless rendering, if `--render` was given.
+///
+/// `edgescan --render out.png input.vcd [--width 1920] [--height 1080] [--from <t>] [--to <t>]`
+fn parse_render_args(args: &[OsString]) -> Result<Option<RenderArgs>, Error> {
+    if !args.iter().any(|arg| arg == "--render") {
+        return Ok(None);
+    }
+
+    let mut output = None;
+    let mut input = None;
+    let mut width = 1920;
+    let mut height = 1080;
+    let mut from = None;
+    let mut to = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| Error::Args(format!("`{name}` requires a value")))
+        };
+        match arg.to_str() {
+            Some("--render") => output = Some(PathBuf::from(value("--render")?)),
+            Some(name @ "--width") => width = parse_number(name, value(name)?)?,
+            Some(name @ "--height") => height = parse_number(name, value(name)?)?,
+            Some(name @ "--from") => from = Some(parse_number(name, value(name)?)?),
+            Some(name @ "--to") => to = Some(parse_number(name, value(name)?)?),
+            Some(name) if name.starts_with("--") => {
+                return Err(Error::Args(format!("unknown option `{name}`")));
+            }
+            _ => input = Some(PathBuf::from(arg)),
+        }
+    }
+
+    let input = input.ok_or_else(|| Error::Args("no input file".to_string()))?;
+    if width == 0 || height == 0 {
+        return Err(Error::Args("the image size must not be zero".to_string()));
+    }
+
+    Ok(Some(RenderArgs {
+        output: output.unwrap(),
+        input,
+        width,
+        height,
+        from,
+        to,
+    }))
+}
+
+/// Parse the value of a numeric option.
+fn parse_number<T: FromStr>(name: &str, value: &OsString) -> Result<T, Error> {
+    value
+        .to_str()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| Error::Args(format!("`{name}` requires a number")))
+}
+
+/// Render a waveform file to an image without opening a window.
+fn render(args: RenderArgs) -> Result<(), Error> {
+    let vcd = load_vcd(&args.input)?;
+    let backends = wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::PRIMARY);
+    let gpu = Gpu::headless(backends)?;
+
+    let max_size = gpu.max_texture_size();
+    if args.width > max_size || args.height > max_size {
+        let msg = format!("the image size must not exceed {max_size}x{max_size}");
+        return Err(Error::Args(msg));
+    }
+
+    framework::render_to_file(
+        &gpu,
+        args.input,
+        vcd,
+        [args.width, args.height],
+        (args.from, args.to),
+        &args.output,
+    )?;
+
+    Ok(())
+}
+
+/// Load a VCD (optionally gzip-compressed) or FST file.
+///
+/// The VCD is read from standard input when the path is `-`.
+fn load_vcd(path: &Path) -> Result<Waveform, Error> {
+    let waveform = if path == Path::new(STDIN_PATH) {
+        Waveform::from_reader(path, std::io::stdin().lock())
+    } else {
+        Waveform::load_path(path)
+    }?;
+
+    Ok(waveform)
 }
 
 fn run() -> Result<(), Error> {
-    let config = Config::new()?;
-    let event_loop = EventLoop::new();
+    // Headless rendering doesn't need a window or the config
+    let args: Vec<_> = std::env::args_os().skip(1).collect();
+    if let Some(args) = parse_render_args(&args)? {
+        return render(args);
+    }
+
+    let mut config = Config::new()?;
+
+    // Open the file in the running instance, if there is one
+    let path = std::env::args_os().nth(1).map(PathBuf::from);
+    if config.single_instance() {
+        if let Some(path) = path.as_ref().filter(|path| *path != Path::new(STDIN_PATH)) {
+            if instance::forward(&config.instance_port_path(), path) {
+                return Ok(());
+            }
+        }
+    }
+
+    // Files sent by other instances, and changes to open files, arrive as user events
+    let event_loop = EventLoopBuilder::<UserEvent>::with_user_event().build();
+    let proxy = event_loop.create_proxy();
+    if config.single_instance() {
+        if let Err(err) = instance::listen(&config.instance_port_path(), proxy.clone()) {
+            warn!("Unable to listen for files from other instances: {err}");
+        }
+    }
     let mut input = WinitInputHelper::new();
     let (window, mut framework) = {
         let (width, height) = config.get_window_size();
+        config.normalize_window_position(
+            event_loop.available_monitors(),
+            event_loop.primary_monitor(),
+        );
 
-        let window = WindowBuilder::new()
+        let mut builder = WindowBuilder::new()
             .with_title("EdgeScan")
             .with_inner_size(LogicalSize::new(width, height))
-            .build(&event_loop)?;
+            .with_transparent(config.transparent_background());
+        if let Some((x, y)) = config.get_window_position() {
+            builder = builder.with_position(PhysicalPosition::new(x, y));
+        }
+        if config.fullscreen() {
+            builder = builder.with_fullscreen(Some(Fullscreen::Borderless(None)));
+        }
+        let window = builder.build(&event_loop)?;
+
+        // The standard `WGPU_BACKEND` environment variable overrides the config
+        let backends =
+            wgpu::util::backend_bits_from_env().unwrap_or_else(|| config.backend().backends());
 
         // SAFETY: The window is moved into the event_loop run closure, ensuring it lives at least
         // as long as `gpu`
-        let gpu = unsafe { Gpu::new(&window, window.inner_size())? };
+        let present_mode = config.present_mode().present_mode();
+        let adapter = config.adapter();
+        let gpu = unsafe {
+            Gpu::new(
+                &window,
+                window.inner_size(),
+                backends,
+                present_mode,
+                adapter,
+                config.sample_count(),
+                config.transparent_background(),
+            )?
+        };
+
+        // A saved window may be larger than this GPU can render to
+        if config.limit_window_size(gpu.max_texture_size(), window.scale_factor()) {
+            let (width, height) = config.get_window_size();
+            window.set_inner_size(LogicalSize::new(width, height));
+        }
 
+        // The file is loaded in the background, showing its progress; a file that can't be loaded
+        // leaves an empty view
         let framework = Framework::new(
             &event_loop,
             window.inner_size(),
             window.scale_factor(),
+            window.theme(),
             config,
             gpu,
+            path,
+            proxy,
         );
 
         (window, framework)
@@ -62,49 +245,80 @@ fn run() -> Result<(), Error> {
     event_loop.run(move |event, _, control_flow| {
         // Handle input events
         if input.update(&event) {
-            // Close events
-            if input.close_requested() {
-                if let Err(err) = framework.config().save() {
-                    handle_error(Error::from(err));
-                }
-
+            // Close events, including a quit requested from the GUI on the previous update
+            if input.close_requested() || framework.quit_requested() {
                 *control_flow = ControlFlow::Exit;
                 return;
             }
 
+            if input.key_pressed(VirtualKeyCode::F11) {
+                toggle_fullscreen(&window, framework.config());
+            }
+
             // Resize the window
             if let Some(size) = input.window_resized() {
                 framework.resize(size, window.scale_factor());
+                save_window_position(&window, &mut framework);
             }
 
-            // Update internal state and request a redraw
+            // Update internal state and schedule the next redraw
             repaint = framework.prepare(&window);
-            maybe_redraw(control_flow, &window, repaint.is_zero());
+            if framework.quit_requested() {
+                repaint = Duration::ZERO;
+            }
+            schedule_redraw(control_flow, &window, repaint);
         }
 
         match event {
             Event::WindowEvent { event, .. } => {
+                match &event {
+                    WindowEvent::Moved(_) => save_window_position(&window, &mut framework),
+
+                    // Moved to a monitor with a different DPI
+                    WindowEvent::ScaleFactorChanged {
+                        scale_factor,
+                        new_inner_size,
+                    } => framework.resize(**new_inner_size, *scale_factor),
+
+                    _ => (),
+                }
+
                 // Update egui inputs
-                maybe_redraw(
-                    control_flow,
-                    &window,
-                    framework.handle_event(&event).repaint,
-                );
+                if framework.handle_event(&event).repaint {
+                    schedule_redraw(control_flow, &window, Duration::ZERO);
+                }
+            }
+            Event::NewEvents(StartCause::ResumeTimeReached { .. }) => {
+                // egui asked to be repainted at this time
+                window.request_redraw();
+            }
+            Event::UserEvent(UserEvent::Open(path)) => {
+                framework.open_file(path);
+                window.focus_window();
             }
+            Event::UserEvent(UserEvent::FileChanged) => window.request_redraw(),
             Event::RedrawRequested(_) => {
                 // Draw the current frame
                 if let Err(err) = framework.render() {
-                    error!("framework.render() failed: {err}");
+                    show_error(Error::from(err));
                     *control_flow = ControlFlow::Exit;
                     return;
                 }
-                maybe_redraw(control_flow, &window, repaint.is_zero());
+                schedule_redraw(control_flow, &window, repaint);
+            }
+            Event::LoopDestroyed => {
+                // Save on every exit, including render errors, so settings are never lost
+                framework.save_sessions();
+                if let Err(err) = framework.config().save() {
+                    show_error(Error::from(err));
+                }
             }
             Event::RedrawEventsCleared => {
-                // TODO: `ControlFlow::Wait` doesn't work on macOS.
+                // TODO: `ControlFlow::Wait` doesn't work on macOS. Limit continuous repaints to
+                // 60 fps.
                 // See: https://github.com/rust-windowing/winit/issues/1985
                 #[cfg(target_os = "macos")]
-                {
+                if *control_flow == ControlFlow::Poll {
                     let target = Duration::from_secs_f64(1.0 / 60.0);
                     let actual = now.elapsed();
                     if target > actual {
@@ -119,37 +333,47 @@ fn run() -> Result<(), Error> {
     });
 }
 
-fn maybe_redraw(control_flow: &mut ControlFlow, window: &Window, do_it: bool) {
-    if do_it {
+/// Remember the window's outer position so it can be restored at startup.
+fn save_window_position(window: &Window, framework: &mut Framework) {
+    // Not all platforms can report the window position
+    if let Ok(position) = window.outer_position() {
+        framework
+            .config()
+            .set_window_position(position.x, position.y);
+    }
+}
+
+/// Enter or leave full screen on the current monitor.
+///
+/// The config keeps the windowed size and position while the window is full screen, so they are
+/// restored on the next start.
+fn toggle_fullscreen(window: &Window, config: &mut Config) {
+    let fullscreen = window.fullscreen().is_none();
+    config.set_fullscreen(fullscreen);
+    window.set_fullscreen(fullscreen.then_some(Fullscreen::Borderless(None)));
+}
+
+/// Redraw now if `repaint` is zero, otherwise sleep until egui needs to be repainted.
+///
+/// egui returns [`Duration::MAX`] when it does not need to be repainted until the next input event.
+fn schedule_redraw(control_flow: &mut ControlFlow, window: &Window, repaint: Duration) {
+    if repaint.is_zero() {
         window.request_redraw();
         *control_flow = ControlFlow::Poll;
+    } else if let Some(deadline) = Instant::now().checked_add(repaint) {
+        *control_flow = ControlFlow::WaitUntil(deadline);
     } else {
         *control_flow = ControlFlow::Wait;
     }
 }
 
-fn handle_error(err: Error) {
-    error!("{err}");
-    for source in err.sources().skip(1) {
-        error!("  Caused by: {source}");
-    }
-
-    // TODO: Make fatal errors nice
-    MessageDialog::new()
-        .set_title("Error")
-        .set_description(&err.to_string())
-        .set_level(MessageLevel::Error)
-        .set_buttons(MessageButtons::Ok)
-        .show();
-}
-
 fn main() -> ExitCode {
     env_logger::init();
 
     match run() {
         Ok(_) => ExitCode::SUCCESS,
         Err(err) => {
-            handle_error(err);
+            show_error(err);
 
             ExitCode::FAILURE
         }
@@ -0,0 +1,334 @@
+//! Formatting multi-bit signal values.
+
+use dwfv::signaldb::{BitValue, SignalValue};
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+
+/// A value to search for, which may contain don't-care bits.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Pattern {
+    /// Bits from least to most significant, `None` for don't-care.
+    bits: Vec<Option<bool>>,
+    /// Value of the bits above `bits`: set for negative numbers.
+    negative: bool,
+}
+
+/// Number base used to display multi-bit values.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Radix {
+    #[default]
+    Hexadecimal,
+    Decimal,
+    SignedDecimal,
+    Binary,
+}
+
+impl Radix {
+    /// All radixes, in the order they are presented to the user.
+    pub const ALL: [Radix; 4] = [
+        Radix::Hexadecimal,
+        Radix::Decimal,
+        Radix::SignedDecimal,
+        Radix::Binary,
+    ];
+
+    /// Format a multi-bit value in this radix.
+    ///
+    /// The first bit is the most significant. Bits that are not High or Low are shown as `z` or
+    /// `x` when the radix cannot represent them.
+    pub fn format(self, bits: &[BitValue]) -> String {
+        match self {
+            Self::Hexadecimal => format_hex(bits),
+            Self::Decimal => format_decimal(bits, false),
+            Self::SignedDecimal => format_decimal(bits, true),
+            Self::Binary => bits.iter().map(bit_char).collect(),
+        }
+    }
+
+    /// Parse a search pattern written in this radix.
+    ///
+    /// A `0x` or `0b` prefix overrides the radix. Hexadecimal and binary patterns may contain `?`
+    /// for don't-care digits, and `_` separators. Returns `None` if the pattern is invalid.
+    pub fn parse_pattern(self, text: &str) -> Option<Pattern> {
+        let text: String = text
+            .trim()
+            .chars()
+            .filter(|&c| c != '_')
+            .collect::<String>()
+            .to_ascii_lowercase();
+        let (radix, digits) = if let Some(digits) = text.strip_prefix("0x") {
+            (Self::Hexadecimal, digits)
+        } else if let Some(digits) = text.strip_prefix("0b") {
+            (Self::Binary, digits)
+        } else {
+            (self, text.as_str())
+        };
+        if digits.is_empty() {
+            return None;
+        }
+
+        let (bits_per_digit, base) = match radix {
+            Self::Hexadecimal => (4, 16),
+            Self::Binary => (1, 2),
+            Self::Decimal | Self::SignedDecimal => return Pattern::from_decimal(digits),
+        };
+
+        let mut bits = Vec::new();
+        for c in digits.chars().rev() {
+            if c == '?' {
+                bits.extend(std::iter::repeat(None).take(bits_per_digit));
+            } else {
+                let digit = c.to_digit(base)?;
+                bits.extend((0..bits_per_digit).map(|bit| Some(digit & (1 << bit) != 0)));
+            }
+        }
+
+        Some(Pattern {
+            bits,
+            negative: false,
+        })
+    }
+
+    /// Format a signal value in this radix.
+    pub fn format_value(self, value: &SignalValue) -> String {
+        match value {
+            SignalValue::Literal(bits, _) => self.format(bits),
+            SignalValue::Symbol(symbol) => symbol.to_string(),
+        }
+    }
+
+    /// Interpret a signal value as a number, for plotting.
+    ///
+    /// Values are two's-complement in [`Radix::SignedDecimal`], and unsigned in all other radixes.
+    /// Returns `None` for symbols, values wider than 128 bits, and values with bits that are
+    /// neither High nor Low.
+    pub fn to_f64(self, value: &SignalValue) -> Option<f64> {
+        let bits = match value {
+            SignalValue::Literal(bits, _) if bits.len() <= 128 => bits,
+            _ => return None,
+        };
+
+        if self == Self::SignedDecimal {
+            to_signed(bits).map(|value| value as f64)
+        } else {
+            to_unsigned(bits).map(|value| value as f64)
+        }
+    }
+}
+
+/// Reverse the bit order of a value, for buses that were dumped with their bits the other way
+/// around.
+pub fn reverse_bits(value: SignalValue) -> SignalValue {
+    match value {
+        SignalValue::Literal(mut bits, width) => {
+            bits.reverse();
+            SignalValue::Literal(bits, width)
+        }
+        symbol => symbol,
+    }
+}
+
+impl fmt::Display for Radix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Hexadecimal => "Hexadecimal",
+            Self::Decimal => "Decimal",
+            Self::SignedDecimal => "Signed Decimal",
+            Self::Binary => "Binary",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+fn bit_char(bit: &BitValue) -> char {
+    match bit {
+        BitValue::Low => '0',
+        BitValue::High => '1',
+        BitValue::HighZ => 'z',
+        _ => 'x',
+    }
+}
+
+/// Interpret the bits as an unsigned integer, if they are all High or Low.
+fn to_unsigned(bits: &[BitValue]) -> Option<u128> {
+    bits.iter().try_fold(0, |value, bit| match bit {
+        BitValue::Low => Some(value << 1),
+        BitValue::High => Some((value << 1) | 1),
+        _ => None,
+    })
+}
+
+/// Interpret the bits as a two's-complement integer, if they are all High or Low.
+///
+/// Values wider than 128 bits are truncated.
+fn to_signed(bits: &[BitValue]) -> Option<i128> {
+    let value = to_unsigned(bits)?;
+
+    // Sign-extend to 128 bits
+    let value = if matches!(bits.first(), Some(BitValue::High)) && bits.len() < 128 {
+        value | (u128::MAX << bits.len())
+    } else {
+        value
+    };
+
+    Some(value as i128)
+}
+
+/// A single character describing a value that has bits which are neither High nor Low.
+fn format_unknown(bits: &[BitValue]) -> String {
+    if bits.iter().all(|bit| matches!(bit, BitValue::HighZ)) {
+        "z".to_string()
+    } else {
+        "x".to_string()
+    }
+}
+
+/// Format as hexadecimal, one digit per nibble.
+fn format_hex(bits: &[BitValue]) -> String {
+    bits.rchunks(4)
+        .rev()
+        .map(|nibble| match to_unsigned(nibble) {
+            Some(digit) => char::from_digit(digit as u32, 16).unwrap(),
+            None => format_unknown(nibble).remove(0),
+        })
+        .collect()
+}
+
+/// Format as decimal, optionally interpreting the bits as two's-complement.
+///
+/// Values wider than 128 bits fall back to hexadecimal.
+fn format_decimal(bits: &[BitValue], signed: bool) -> String {
+    if bits.len() > 128 {
+        return format_hex(bits);
+    }
+
+    let value = if signed {
+        to_signed(bits).map(|value| value.to_string())
+    } else {
+        to_unsigned(bits).map(|value| value.to_string())
+    };
+
+    value.unwrap_or_else(|| format_unknown(bits))
+}
+
+impl Pattern {
+    /// Parse a decimal number, which may be negative.
+    fn from_decimal(digits: &str) -> Option<Self> {
+        let value: i128 = digits.parse().ok()?;
+        let negative = value < 0;
+        let mut bits: Vec<_> = (0..128).map(|bit| Some(value & (1 << bit) != 0)).collect();
+
+        // Keep the shortest two's-complement representation; the rest extends it
+        while bits.len() > 1 && bits.last() == Some(&Some(negative)) {
+            if negative && bits[bits.len() - 2] != Some(true) {
+                break;
+            }
+            bits.pop();
+        }
+        if !negative && bits == [Some(false)] {
+            bits.clear();
+        }
+
+        Some(Self { bits, negative })
+    }
+
+    /// Check whether a signal value matches this pattern.
+    ///
+    /// Bits that are neither High nor Low never match, except as don't-care bits.
+    pub fn matches(&self, value: &SignalValue) -> bool {
+        let bits = match value {
+            SignalValue::Literal(bits, _) => bits,
+            SignalValue::Symbol(_) => return false,
+        };
+
+        (0..bits.len().max(self.bits.len())).all(|i| {
+            let expected = self.bits.get(i).copied().unwrap_or(Some(self.negative));
+            match (expected, bits.len().checked_sub(i + 1).map(|i| &bits[i])) {
+                (None, _) => true,
+                // The pattern is wider than the signal; it must only extend the value, and a
+                // negative value only extends a signal with its sign bit set
+                (Some(expected), None) => {
+                    expected == self.negative
+                        && (!self.negative || matches!(bits.first(), Some(BitValue::High)))
+                }
+                (Some(true), Some(bit)) => matches!(bit, BitValue::High),
+                (Some(false), Some(bit)) => matches!(bit, BitValue::Low),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A literal value from bits written most significant first, as `0`, `1` or `z`.
+    fn literal(text: &str) -> SignalValue {
+        let bits: Vec<_> = text
+            .chars()
+            .map(|c| match c {
+                '0' => BitValue::Low,
+                '1' => BitValue::High,
+                _ => BitValue::HighZ,
+            })
+            .collect();
+        let width = bits.len();
+
+        SignalValue::Literal(bits, width as _)
+    }
+
+    #[test]
+    fn patterns_match_values() {
+        let patterns = [
+            // Don't-care hexadecimal digits
+            (Radix::Hexadecimal, "0x3?", "00111010", true),
+            (Radix::Hexadecimal, "3?", "00110000", true),
+            (Radix::Decimal, "0x3?", "01001010", false),
+            (Radix::Hexadecimal, "3?", "0011zzzz", true),
+            (Radix::Hexadecimal, "3?", "001z0000", false),
+            // Don't-care binary digits
+            (Radix::Binary, "1?0", "110", true),
+            (Radix::Binary, "1_?_0", "100", true),
+            (Radix::Binary, "1?0", "111", false),
+            (Radix::Decimal, "0b1?0", "0100", true),
+            // Negative numbers are sign-extended to the width of the signal
+            (Radix::SignedDecimal, "-2", "10", true),
+            (Radix::SignedDecimal, "-2", "1110", true),
+            (Radix::SignedDecimal, "-2", "11111110", true),
+            (Radix::SignedDecimal, "-2", "0110", false),
+            (Radix::SignedDecimal, "-1", "1", true),
+            (Radix::Decimal, "5", "00000101", true),
+            // A pattern wider than the signal only matches if the extra bits extend the value
+            (Radix::Hexadecimal, "0x0ff", "11111111", true),
+            (Radix::Hexadecimal, "0x1ff", "11111111", false),
+            (Radix::Decimal, "256", "00000000", false),
+            (Radix::SignedDecimal, "-200", "00111000", false),
+        ];
+
+        for (radix, text, value, expected) in patterns {
+            let pattern = radix.parse_pattern(text).unwrap();
+            assert_eq!(
+                pattern.matches(&literal(value)),
+                expected,
+                "{text:?} {value} {radix}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_rejects_invalid_patterns() {
+        let patterns = [
+            (Radix::Hexadecimal, ""),
+            (Radix::Hexadecimal, "0x"),
+            (Radix::Hexadecimal, "0xg"),
+            (Radix::Binary, "102"),
+            (Radix::Decimal, "?"),
+            (Radix::SignedDecimal, "1.5"),
+        ];
+
+        for (radix, text) in patterns {
+            assert_eq!(radix.parse_pattern(text), None, "{text:?} {radix}");
+        }
+    }
+}
@@ -0,0 +1,365 @@
+//! Reading a VCD that a running simulation streams over TCP, or writes to a named pipe.
+//!
+//! A [`SignalDB`](dwfv::signaldb::SignalDB) can't be appended to, so the dump received so far is
+//! parsed again when it grows. Each parse waits at least [`UPDATE_INTERVAL`], and a few times as
+//! long as the last parse took, so the dump grows geometrically between parses and the total time
+//! spent parsing stays proportional to its length. Only complete lines are parsed; a value change
+//! split across packets waits for the rest of it to arrive.
+//!
+//! The bytes received are kept for the next parse, so a stream is read up to [`MAX_LEN`] bytes.
+//! The connection is closed with [`Error::TooLarge`] once a dump grows past that, and the
+//! waveform sent last stays valid.
+
+use crate::waveform::{self, LoadError, Waveform, END_DEFINITIONS};
+use std::fs::File;
+use std::io::{self, Read};
+use std::net::TcpStream;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryIter, TryRecvError};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// How often the dump is parsed while it is growing, at most.
+pub const UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Largest dump read from a stream, in bytes.
+pub const MAX_LEN: usize = 512 * 1024 * 1024;
+
+/// Time to wait between parses, relative to how long the last parse took.
+const PARSE_BACKOFF: u32 = 4;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Unable to connect to `{0}`")]
+    Connect(String, #[source] io::Error),
+
+    #[error("Lost the connection to `{0}`")]
+    Read(String, #[source] io::Error),
+
+    #[error("`{0}` closed the connection before sending a VCD header")]
+    Empty(String),
+
+    #[error("`{0}` sent more than {} MiB; the rest of the stream was ignored", MAX_LEN >> 20)]
+    TooLarge(String),
+
+    #[error("Unable to parse the VCD sent by `{0}`")]
+    Parse(String, #[source] LoadError),
+}
+
+/// A message from the thread reading a stream.
+#[derive(Debug)]
+pub enum Update {
+    /// The whole dump received so far.
+    Waveform(Waveform),
+
+    /// The connection has ended. Waveforms sent before it are still valid.
+    Closed(Option<Error>),
+}
+
+/// The receiving end of a stream that is read on a background thread.
+///
+/// Dropping it closes the connection.
+#[derive(Debug)]
+pub struct Connection {
+    receiver: Receiver<Update>,
+
+    // The reading thread stops once this is dropped.
+    _open: Arc<()>,
+}
+
+/// The bytes received from a stream.
+#[derive(Default)]
+struct Dump {
+    buf: Vec<u8>,
+
+    // Length of the dump up to its last complete line, and whether that contains the header.
+    complete: usize,
+    header_read: bool,
+
+    // Length of the dump when it was last parsed, and the error if that failed.
+    parsed: usize,
+    error: Option<LoadError>,
+
+    // Whether a waveform has been sent.
+    sent: bool,
+}
+
+/// Connect to a simulation and read a VCD from it on a background thread.
+///
+/// `address` is a `host:port` to connect to over TCP, or the path of a named pipe that the
+/// simulation writes to: a FIFO, or `\\.\pipe\name` on Windows. The connection gets a waveform
+/// each time the dump grows, and [`Update::Closed`] last.
+pub fn connect(address: String) -> Connection {
+    let (sender, receiver) = mpsc::channel();
+    let open = Arc::new(());
+    let weak = Arc::downgrade(&open);
+
+    std::thread::spawn(move || {
+        let result =
+            open_source(&address).and_then(|source| read(source, &address, &sender, &weak));
+
+        // The receiver is gone if the document was closed
+        let _ = sender.send(Update::Closed(result.err()));
+    });
+
+    Connection {
+        receiver,
+        _open: open,
+    }
+}
+
+impl Connection {
+    /// Take the next update, if one has arrived.
+    pub fn try_recv(&self) -> Result<Update, TryRecvError> {
+        self.receiver.try_recv()
+    }
+
+    /// Take all updates that have arrived.
+    pub fn try_iter(&self) -> TryIter<'_, Update> {
+        self.receiver.try_iter()
+    }
+}
+
+/// Open the named pipe at `address`, or connect to it over TCP if it isn't one.
+///
+/// Opening a FIFO waits for the simulation to open it for writing.
+fn open_source(address: &str) -> Result<Box<dyn Read + Send>, Error> {
+    let connect_err = |err| Error::Connect(address.to_string(), err);
+
+    if address.starts_with(r"\\.\pipe\") || Path::new(address).exists() {
+        let pipe = File::open(address).map_err(connect_err)?;
+
+        return Ok(Box::new(pipe));
+    }
+
+    // Wake up regularly, so a closed document is noticed while the stream is quiet
+    let stream = TcpStream::connect(address).map_err(connect_err)?;
+    stream
+        .set_read_timeout(Some(UPDATE_INTERVAL))
+        .map_err(connect_err)?;
+
+    Ok(Box::new(stream))
+}
+
+/// Read the stream until it ends or the connection is dropped, sending the dump whenever it has
+/// grown.
+fn read(
+    source: Box<dyn Read + Send>,
+    address: &str,
+    sender: &Sender<Update>,
+    open: &Weak<()>,
+) -> Result<(), Error> {
+    let chunks = read_chunks(source, open.clone());
+
+    let mut dump = Dump::default();
+    let mut next_update = Instant::now();
+    loop {
+        if open.strong_count() == 0 {
+            return Ok(());
+        }
+
+        // Wake up regularly, so a dump that stops growing is still sent
+        match chunks.recv_timeout(UPDATE_INTERVAL) {
+            Ok(Ok(chunk)) if dump.buf.len() + chunk.len() > MAX_LEN => {
+                // Parse what fits, ending it at its last complete line
+                dump.extend(&chunk[..MAX_LEN - dump.buf.len()]);
+                dump.update(address, sender);
+                return Err(Error::TooLarge(address.to_string()));
+            }
+            Ok(Ok(chunk)) => dump.extend(&chunk),
+            Ok(Err(err)) => {
+                // Keep what was received before the connection was lost
+                dump.update(address, sender);
+                return Err(Error::Read(address.to_string(), err));
+            }
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        if Instant::now() >= next_update {
+            let started = Instant::now();
+            dump.update(address, sender);
+            next_update = Instant::now() + UPDATE_INTERVAL.max(started.elapsed() * PARSE_BACKOFF);
+        }
+    }
+
+    // The last line may not end with a newline
+    dump.complete_to(dump.buf.len());
+    dump.update(address, sender);
+
+    match dump.error.take() {
+        Some(err) => Err(Error::Parse(address.to_string(), err)),
+        None if !dump.sent => Err(Error::Empty(address.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Read chunks of the stream on another thread, until it ends, fails, or `open` is dropped.
+///
+/// Reads from a named pipe block until the simulation writes more, so they can't wait on the thread
+/// that sends the dump. A thread blocked on a quiet pipe ends once the simulation writes to it or
+/// closes it.
+fn read_chunks(mut source: Box<dyn Read + Send>, open: Weak<()>) -> Receiver<io::Result<Vec<u8>>> {
+    let (sender, receiver) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        let mut chunk = vec![0; 64 * 1024];
+        while open.strong_count() > 0 {
+            let result = match source.read(&mut chunk) {
+                Ok(0) => return,
+                Ok(len) => Ok(chunk[..len].to_vec()),
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::Interrupted
+                            | io::ErrorKind::WouldBlock
+                            | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    continue
+                }
+                Err(err) => Err(err),
+            };
+
+            let failed = result.is_err();
+            if sender.send(result).is_err() || failed {
+                return;
+            }
+        }
+    });
+
+    receiver
+}
+
+impl Dump {
+    /// Add bytes received from the stream.
+    fn extend(&mut self, bytes: &[u8]) {
+        let start = self.buf.len();
+        self.buf.extend_from_slice(bytes);
+
+        // Only the new bytes are searched
+        if let Some(index) = bytes.iter().rposition(|&byte| byte == b'\n') {
+            self.complete_to(start + index + 1);
+        }
+    }
+
+    /// Mark the dump as complete up to `len` bytes, noting whether its header has ended.
+    ///
+    /// The keyword can't span a newline, so only the newly completed lines are searched for it.
+    fn complete_to(&mut self, len: usize) {
+        if !self.header_read {
+            self.header_read = waveform::contains(&self.buf[self.complete..len], END_DEFINITIONS);
+        }
+        self.complete = len;
+    }
+
+    /// Parse the complete lines received so far and send them, if there are more than last time.
+    ///
+    /// A dump that fails to parse is kept, and parsed again when more of it arrives.
+    fn update(&mut self, address: &str, sender: &Sender<Update>) {
+        // Signals are only known once the header has ended
+        if !self.header_read || self.complete <= self.parsed {
+            return;
+        }
+        self.parsed = self.complete;
+
+        // The address only names the stream in errors; its format isn't sniffed from it
+        match Waveform::from_vcd(address, &self.buf[..self.complete]) {
+            Ok(waveform) => {
+                self.error = None;
+                self.sent = true;
+
+                // The receiver is gone if the document was closed; the read loop notices that
+                let _ = sender.send(Update::Waveform(waveform));
+            }
+            Err(err) => self.error = Some(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "$timescale 1 ns $end\n\
+                          $scope module top $end\n\
+                          $var wire 1 ! clk $end\n\
+                          $upscope $end\n\
+                          $enddefinitions $end\n";
+
+    /// The last timestamp of each waveform sent.
+    fn sent(receiver: &Receiver<Update>) -> Vec<Option<i64>> {
+        receiver
+            .try_iter()
+            .map(|update| match update {
+                Update::Waveform(waveform) => waveform.time_range().map(|range| *range.end()),
+                Update::Closed(_) => panic!("unexpected close"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn extend_completes_lines() {
+        let mut dump = Dump::default();
+
+        dump.extend(b"#0\n0");
+        assert_eq!(dump.complete, 3);
+
+        // No newline, so nothing more is complete
+        dump.extend(b"!\n#1");
+        assert_eq!(dump.complete, 6);
+        dump.extend(b"0");
+        assert_eq!(dump.complete, 6);
+
+        dump.complete_to(dump.buf.len());
+        assert_eq!(dump.complete, 9);
+        assert!(!dump.header_read);
+    }
+
+    #[test]
+    fn header_split_across_chunks() {
+        let vcd = format!("{HEADER}#0\n0!\n");
+        let split = vcd.find("$enddefinitions").unwrap() + "$enddef".len();
+        let (sender, receiver) = mpsc::channel();
+        let mut dump = Dump::default();
+
+        dump.extend(vcd[..split].as_bytes());
+        assert!(!dump.header_read);
+        dump.update("test", &sender);
+        assert!(sent(&receiver).is_empty());
+
+        // The keyword is only searched for once its line is complete
+        dump.extend(vcd[split..].as_bytes());
+        assert!(dump.header_read);
+        dump.update("test", &sender);
+        assert_eq!(sent(&receiver), [Some(0)]);
+    }
+
+    #[test]
+    fn update_parses_complete_lines() {
+        let (sender, receiver) = mpsc::channel();
+        let mut dump = Dump::default();
+
+        dump.extend(format!("{HEADER}#0\n0!\n#1").as_bytes());
+        dump.update("test", &sender);
+        assert_eq!(sent(&receiver), [Some(0)]);
+
+        // Nothing new is complete, so nothing is sent
+        dump.extend(b"0");
+        dump.update("test", &sender);
+        assert!(sent(&receiver).is_empty());
+
+        dump.extend(b"\n1!\n");
+        dump.update("test", &sender);
+        assert_eq!(sent(&receiver), [Some(10)]);
+
+        // The stream ended without a newline
+        dump.extend(b"#20\n0!");
+        dump.complete_to(dump.buf.len());
+        dump.update("test", &sender);
+        assert_eq!(sent(&receiver), [Some(20)]);
+        assert!(dump.sent);
+        assert!(dump.error.is_none());
+    }
+}
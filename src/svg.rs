@@ -0,0 +1,215 @@
+//! Export the shapes that egui draws for a frame as an SVG image.
+//!
+//! The waveform view is drawn with lines, paths, rectangles and text, which map directly to SVG
+//! elements. This keeps the export identical to the view, without a second drawing routine.
+
+use egui::epaint::{ClippedShape, Shape, Stroke, TextShape};
+use egui::{Color32, FontFamily, Pos2, Rect};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+#[error("Unable to write `{}`", .0.display())]
+pub struct Error(PathBuf, #[source] std::io::Error);
+
+/// Save the shapes inside `area` to an SVG file.
+pub(crate) fn save(path: &Path, shapes: &[ClippedShape], area: Rect) -> Result<(), Error> {
+    let write = || -> std::io::Result<()> {
+        let mut writer = BufWriter::new(std::fs::File::create(path)?);
+        write_svg(&mut writer, shapes, area)?;
+
+        writer.flush()
+    };
+
+    write().map_err(|err| Error(path.into(), err))
+}
+
+/// Write the shapes inside `area` as an SVG document the size of `area`.
+///
+/// Shapes keep their clip rectangles, so text that is cut off in the view is cut off in the image.
+fn write_svg<W: Write>(writer: &mut W, shapes: &[ClippedShape], area: Rect) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="{x} {y} {w} {h}">"#,
+        x = area.min.x,
+        y = area.min.y,
+        w = area.width(),
+        h = area.height(),
+    )?;
+
+    let mut clips: Vec<Rect> = Vec::new();
+    for ClippedShape(clip, shape) in shapes {
+        let clip = clip.intersect(area);
+        if !clip.is_positive() {
+            continue;
+        }
+
+        let id = match clips.iter().position(|other| *other == clip) {
+            Some(id) => id,
+            None => {
+                writeln!(
+                    writer,
+                    r#"<clipPath id="clip{}"><rect x="{}" y="{}" width="{}" height="{}"/></clipPath>"#,
+                    clips.len(),
+                    clip.min.x,
+                    clip.min.y,
+                    clip.width(),
+                    clip.height(),
+                )?;
+                clips.push(clip);
+                clips.len() - 1
+            }
+        };
+
+        writeln!(writer, r#"<g clip-path="url(#clip{id})">"#)?;
+        write_shape(writer, shape)?;
+        writeln!(writer, "</g>")?;
+    }
+
+    writeln!(writer, "</svg>")
+}
+
+fn write_shape<W: Write>(writer: &mut W, shape: &Shape) -> std::io::Result<()> {
+    match shape {
+        Shape::Vec(shapes) => {
+            for shape in shapes {
+                write_shape(writer, shape)?;
+            }
+        }
+        Shape::Circle(circle) => writeln!(
+            writer,
+            r#"<circle cx="{}" cy="{}" r="{}" {} {}/>"#,
+            circle.center.x,
+            circle.center.y,
+            circle.radius,
+            fill(circle.fill),
+            stroke(circle.stroke),
+        )?,
+        Shape::LineSegment {
+            points: [a, b],
+            stroke: line,
+        } => writeln!(
+            writer,
+            r#"<line x1="{}" y1="{}" x2="{}" y2="{}" {}/>"#,
+            a.x,
+            a.y,
+            b.x,
+            b.y,
+            stroke(*line),
+        )?,
+        Shape::Path(path) => {
+            // Open paths are not filled
+            let (element, fill_color) = if path.closed {
+                ("polygon", path.fill)
+            } else {
+                ("polyline", Color32::TRANSPARENT)
+            };
+            writeln!(
+                writer,
+                r#"<{element} points="{}" {} {} stroke-linejoin="round"/>"#,
+                points(&path.points),
+                fill(fill_color),
+                stroke(path.stroke),
+            )?;
+        }
+        Shape::Rect(rect) => writeln!(
+            writer,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{}" {} {}/>"#,
+            rect.rect.min.x,
+            rect.rect.min.y,
+            rect.rect.width(),
+            rect.rect.height(),
+            rect.rounding.nw,
+            fill(rect.fill),
+            stroke(rect.stroke),
+        )?,
+        Shape::Text(text) => write_text(writer, text)?,
+
+        // Images and curves are not drawn in the waveform view
+        _ => (),
+    }
+
+    Ok(())
+}
+
+/// Write each row of laid out text as a text element.
+fn write_text<W: Write>(writer: &mut W, text: &TextShape) -> std::io::Result<()> {
+    let format = text
+        .galley
+        .job
+        .sections
+        .first()
+        .map(|section| &section.format);
+    let size = format.map_or(14.0, |format| format.font_id.size);
+    let family = match format.map(|format| &format.font_id.family) {
+        Some(FontFamily::Monospace) => "monospace",
+        _ => "sans-serif",
+    };
+    let color = text
+        .override_text_color
+        .or(format.map(|format| format.color))
+        .unwrap_or(Color32::GRAY);
+
+    for row in &text.galley.rows {
+        let chars: String = row.glyphs.iter().map(|glyph| glyph.chr).collect();
+        if chars.trim().is_empty() {
+            continue;
+        }
+        let pos = text.pos + row.rect.min.to_vec2();
+        writeln!(
+            writer,
+            r#"<text x="{}" y="{}" font-family="{family}" font-size="{size}" dominant-baseline="hanging" xml:space="preserve" {}>{}</text>"#,
+            pos.x,
+            pos.y,
+            fill(color),
+            escape(&chars),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn points(points: &[Pos2]) -> String {
+    let points: Vec<_> = points
+        .iter()
+        .map(|point| format!("{},{}", point.x, point.y))
+        .collect();
+
+    points.join(" ")
+}
+
+fn fill(color: Color32) -> String {
+    paint("fill", color)
+}
+
+fn stroke(stroke: Stroke) -> String {
+    if stroke.width > 0.0 {
+        format!(
+            r#"{} stroke-width="{}""#,
+            paint("stroke", stroke.color),
+            stroke.width
+        )
+    } else {
+        paint("stroke", Color32::TRANSPARENT)
+    }
+}
+
+/// A fill or stroke color attribute, with its opacity if it is translucent.
+fn paint(attribute: &str, color: Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    match a {
+        0 => format!(r#"{attribute}="none""#),
+        255 => format!("{attribute}=\"#{r:02x}{g:02x}{b:02x}\""),
+        _ => format!(
+            "{attribute}=\"#{r:02x}{g:02x}{b:02x}\" {attribute}-opacity=\"{:.3}\"",
+            a as f32 / 255.0,
+        ),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,327 @@
+//! Converting between time units of a VCD and human-readable times, e.g. `1.5 µs`.
+//!
+//! Times in a dump are integers in units of its timescale, e.g. ticks of `10 ps`. Every view of a
+//! time formats and parses it here, so the timeline, the status bar and the signal tooltips agree
+//! with each other.
+
+use dwfv::signaldb::Scale;
+
+/// Every timescale unit, each 1000 times shorter than the last.
+pub const SCALES: [Scale; 6] = [
+    Scale::Second,
+    Scale::Millisecond,
+    Scale::Microsecond,
+    Scale::Nanosecond,
+    Scale::Picosecond,
+    Scale::Femtosecond,
+];
+
+/// Unit labels, in the same order as [`SCALES`].
+const UNITS: [&str; 6] = ["s", "ms", "µs", "ns", "ps", "fs"];
+
+/// The length of one time unit of a dump, e.g. `10 ps`.
+#[derive(Clone, Copy)]
+pub struct Timescale {
+    /// Number of units in a time unit: 1, 10 or 100.
+    pub multiplier: u32,
+    pub unit: Scale,
+}
+
+impl Timescale {
+    /// Length of one time unit in seconds.
+    pub fn seconds(self) -> f64 {
+        f64::from(self.multiplier) * unit_seconds(self.unit)
+    }
+}
+
+impl From<Scale> for Timescale {
+    fn from(unit: Scale) -> Self {
+        Self {
+            multiplier: 1,
+            unit,
+        }
+    }
+}
+
+/// Unit label for a VCD timescale.
+pub fn unit_label(scale: Scale) -> &'static str {
+    UNITS[steps(scale)]
+}
+
+/// Length of one unit in seconds.
+pub fn unit_seconds(scale: Scale) -> f64 {
+    1000_f64.powi(-(steps(scale) as i32))
+}
+
+/// Format a time in units of the given timescale, in the largest unit that keeps it at least one.
+///
+/// The time is never rounded; a fraction is written with as many digits as it needs, e.g.
+/// `1500` nanoseconds is `1.5 µs` and `1000002` nanoseconds is `1.000002 ms`.
+pub fn format_time(time: i64, timescale: Timescale) -> String {
+    let magnitude = u128::from(time.unsigned_abs()) * u128::from(timescale.multiplier);
+    let base = steps(timescale.unit);
+
+    let mut steps = 0;
+    let mut divisor = 1_u128;
+    while steps < base && magnitude / (divisor * 1000) > 0 {
+        steps += 1;
+        divisor *= 1000;
+    }
+
+    let sign = if time < 0 { "-" } else { "" };
+    let unit = UNITS[base - steps];
+    let whole = magnitude / divisor;
+    let fraction = magnitude % divisor;
+    if fraction == 0 {
+        return format!("{sign}{whole} {unit}");
+    }
+
+    let fraction = format!("{fraction:0width$}", width = steps * 3);
+
+    format!("{sign}{whole}.{} {unit}", fraction.trim_end_matches('0'))
+}
+
+/// Parse a time typed by the user into time units of the given timescale.
+///
+/// The time may have a unit from `s` to `fs`, e.g. `1.5us`; without one it is already in time
+/// units. It is rounded to the nearest time unit. Returns `None` if the text is not a valid time.
+pub fn parse_time(text: &str, timescale: Timescale) -> Option<i64> {
+    let text = text.trim();
+    let split = text.find(char::is_alphabetic).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.trim().parse().ok()?;
+
+    let unit = unit.trim();
+    if unit.is_empty() {
+        return number.is_finite().then(|| number.round() as i64);
+    }
+    let seconds = 1000_f64.powi(-(unit_steps(unit)? as i32));
+    let time = number * seconds / timescale.seconds();
+
+    time.is_finite().then(|| time.round() as i64)
+}
+
+/// Parse the `$timescale` declared in a VCD header, e.g. `1 ns` or `10ps`.
+///
+/// Returns `None` if the timescale is not a multiplier of 1, 10 or 100 followed by a unit from `s`
+/// to `fs`.
+pub fn parse_timescale(text: &str) -> Option<Timescale> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let multiplier = match number {
+        "1" => 1,
+        "10" => 10,
+        "100" => 100,
+        _ => return None,
+    };
+
+    Some(Timescale {
+        multiplier,
+        unit: SCALES[unit_steps(unit.trim())?],
+    })
+}
+
+/// Number of steps of 1000 from seconds down to a unit label. VCDs write microseconds as `us`.
+fn unit_steps(unit: &str) -> Option<usize> {
+    match unit {
+        "us" => Some(2),
+        unit => UNITS.iter().position(|label| *label == unit),
+    }
+}
+
+/// Number of steps of 1000 from seconds down to the scale.
+fn steps(scale: Scale) -> usize {
+    match scale {
+        Scale::Second => 0,
+        Scale::Millisecond => 1,
+        Scale::Microsecond => 2,
+        Scale::Nanosecond => 3,
+        Scale::Picosecond => 4,
+        Scale::Femtosecond => 5,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_selects_largest_unit() {
+        let times = [
+            (0, Scale::Nanosecond, "0 ns"),
+            (999, Scale::Nanosecond, "999 ns"),
+            (1000, Scale::Nanosecond, "1 µs"),
+            (1500, Scale::Nanosecond, "1.5 µs"),
+            (2_000_000, Scale::Nanosecond, "2 ms"),
+            (3_000_000_000, Scale::Nanosecond, "3 s"),
+            (60_000_000_000, Scale::Nanosecond, "60 s"),
+            (1234, Scale::Picosecond, "1.234 ns"),
+            (5, Scale::Second, "5 s"),
+            (1000, Scale::Femtosecond, "1 ps"),
+            (-1500, Scale::Nanosecond, "-1.5 µs"),
+        ];
+
+        for (time, scale, expected) in times {
+            assert_eq!(
+                format_time(time, scale.into()),
+                expected,
+                "{time} {}",
+                unit_label(scale)
+            );
+        }
+    }
+
+    #[test]
+    fn format_does_not_round() {
+        let times = [
+            (1001, Scale::Nanosecond, "1.001 µs"),
+            (1_000_002, Scale::Nanosecond, "1.000002 ms"),
+            (1_000_000_001, Scale::Picosecond, "1.000000001 ms"),
+            (1_999_999, Scale::Microsecond, "1.999999 s"),
+            (i64::MAX, Scale::Femtosecond, "9223.372036854775807 s"),
+            (i64::MIN, Scale::Femtosecond, "-9223.372036854775808 s"),
+        ];
+
+        for (time, scale, expected) in times {
+            assert_eq!(
+                format_time(time, scale.into()),
+                expected,
+                "{time} {}",
+                unit_label(scale)
+            );
+        }
+    }
+
+    #[test]
+    fn parse_units() {
+        let times = [
+            ("42", Scale::Nanosecond, Some(42)),
+            ("1.5us", Scale::Nanosecond, Some(1500)),
+            ("1.5 µs", Scale::Nanosecond, Some(1500)),
+            ("2ms", Scale::Nanosecond, Some(2_000_000)),
+            ("1 s", Scale::Microsecond, Some(1_000_000)),
+            ("3ns", Scale::Picosecond, Some(3000)),
+            ("1000fs", Scale::Picosecond, Some(1)),
+            ("  7 ps  ", Scale::Picosecond, Some(7)),
+            ("-1ns", Scale::Picosecond, Some(-1000)),
+        ];
+
+        for (text, scale, expected) in times {
+            assert_eq!(
+                parse_time(text, scale.into()),
+                expected,
+                "{text:?} {}",
+                unit_label(scale)
+            );
+        }
+    }
+
+    #[test]
+    fn parse_rounds_to_nearest_unit() {
+        let times = [
+            ("1.4", Scale::Nanosecond, Some(1)),
+            ("1.5", Scale::Nanosecond, Some(2)),
+            ("1.4ns", Scale::Nanosecond, Some(1)),
+            ("1.0006us", Scale::Nanosecond, Some(1001)),
+            ("1ps", Scale::Nanosecond, Some(0)),
+            ("600ps", Scale::Nanosecond, Some(1)),
+        ];
+
+        for (text, scale, expected) in times {
+            assert_eq!(
+                parse_time(text, scale.into()),
+                expected,
+                "{text:?} {}",
+                unit_label(scale)
+            );
+        }
+    }
+
+    #[test]
+    fn parse_rejects_invalid_times() {
+        let texts = [
+            "",
+            "ns",
+            "1.5 minutes",
+            "1.5 S",
+            "abc",
+            "1..5ns",
+            "inf",
+            "NaN ns",
+        ];
+
+        for text in texts {
+            assert_eq!(parse_time(text, Scale::Nanosecond.into()), None, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn parse_timescales() {
+        let timescales = [
+            ("1 ns", Some((1, Scale::Nanosecond))),
+            ("10ps", Some((10, Scale::Picosecond))),
+            (" 100 us ", Some((100, Scale::Microsecond))),
+            ("1 s", Some((1, Scale::Second))),
+            ("1fs", Some((1, Scale::Femtosecond))),
+            ("", None),
+            ("ns", None),
+            ("1", None),
+            ("2 ns", None),
+            ("1000 ps", None),
+            ("1.5 ns", None),
+            ("1 min", None),
+        ];
+
+        for (text, expected) in timescales {
+            assert_eq!(
+                parse_timescale(text)
+                    .map(|timescale| (timescale.multiplier, unit_label(timescale.unit))),
+                expected.map(|(multiplier, unit)| (multiplier, unit_label(unit))),
+                "{text:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn multiplier_scales_times() {
+        let ten_ns = Timescale {
+            multiplier: 10,
+            unit: Scale::Nanosecond,
+        };
+        let hundred_ps = Timescale {
+            multiplier: 100,
+            unit: Scale::Picosecond,
+        };
+
+        assert_eq!(format_time(15, ten_ns), "150 ns");
+        assert_eq!(format_time(100, ten_ns), "1 µs");
+        assert_eq!(format_time(12, hundred_ps), "1.2 ns");
+        assert_eq!(format_time(i64::MIN, ten_ns), "-92233720368.54775808 s");
+        assert_eq!(parse_time("1us", ten_ns), Some(100));
+        assert_eq!(parse_time("1.2ns", hundred_ps), Some(12));
+        assert_eq!(parse_time("7", ten_ns), Some(7));
+        assert!((ten_ns.seconds() - 1.0e-8).abs() < 1.0e-20);
+    }
+
+    #[test]
+    fn format_round_trips_through_parse() {
+        let times = [0, 1, 999, 1000, 1500, 123_456_789, -42];
+        let timescales = [
+            Scale::Picosecond.into(),
+            Timescale {
+                multiplier: 10,
+                unit: Scale::Picosecond,
+            },
+        ];
+
+        for timescale in timescales {
+            for time in times {
+                let text = format_time(time, timescale);
+                assert_eq!(parse_time(&text, timescale), Some(time), "{text:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Watching open files for changes on a background thread.
+//!
+//! The files are polled, since a change is only acted on while the window is idle anyway. The
+//! event loop is only woken when a file has changed, so an idle window is not repainted to check.
+
+use crate::framework::UserEvent;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryIter};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use winit::event_loop::EventLoopProxy;
+
+/// How often to check the watched files for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The modification time and size of a file, which change when it is written.
+pub type FileStamp = (SystemTime, u64);
+
+/// Polls a set of files on a background thread, sending the paths of those that change.
+///
+/// Dropping it stops the thread.
+pub struct Watcher {
+    // Watched files, with their stamps when they were last seen. Shared with the polling thread.
+    files: Arc<Mutex<Vec<(PathBuf, Option<FileStamp>)>>>,
+    changed: Receiver<PathBuf>,
+}
+
+impl Watcher {
+    /// Start polling. `proxy` receives [`UserEvent::FileChanged`] when changed paths are waiting.
+    pub fn new(proxy: EventLoopProxy<UserEvent>) -> Self {
+        let files = Arc::new(Mutex::new(Vec::new()));
+        let weak = Arc::downgrade(&files);
+        let (sender, changed) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+                let files = match weak.upgrade() {
+                    Some(files) => files,
+                    None => break,
+                };
+
+                let mut found = false;
+                for (path, stamp) in files.lock().unwrap().iter_mut() {
+                    let current = file_stamp(path);
+                    if current.is_some() && current != *stamp {
+                        // Each change is sent once, until the stamp is replaced by `watch`
+                        *stamp = current;
+                        found |= sender.send(path.clone()).is_ok();
+                    }
+                }
+
+                // The event loop is gone when the window is closing
+                if found && proxy.send_event(UserEvent::FileChanged).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self { files, changed }
+    }
+
+    /// Replace the watched files, with the stamps they had when they were loaded.
+    ///
+    /// A file with no stamp is reported as changed as soon as it can be read.
+    pub fn watch(&self, files: Vec<(PathBuf, Option<FileStamp>)>) {
+        *self.files.lock().unwrap() = files;
+    }
+
+    /// Take the paths of the files that have changed since they were last reported.
+    pub fn changed(&self) -> TryIter<'_, PathBuf> {
+        self.changed.try_iter()
+    }
+}
+
+/// Get the modification time and size of a file.
+pub fn file_stamp(path: &Path) -> Option<FileStamp> {
+    let metadata = std::fs::metadata(path).ok()?;
+
+    Some((metadata.modified().ok()?, metadata.len()))
+}
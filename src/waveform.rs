@@ -0,0 +1,700 @@
+//! Loading and querying waveforms without the GUI.
+//!
+//! [`Waveform`] wraps the [`WaveformSource`] model that the viewer draws, with the same file
+//! format support: VCD and gzip-compressed VCD, parsed into a [`SignalDB`], and FST.
+
+use crate::fst;
+use dwfv::signaldb::{SignalDB, SignalValue, Timestamp};
+use flate2::bufread::GzDecoder;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Read, Seek as _};
+use std::ops::{Range, RangeInclusive};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use thiserror::Error;
+
+/// The first two bytes of a gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The keyword that ends the header of a VCD.
+pub(crate) const END_DEFINITIONS: &[u8] = b"$enddefinitions";
+
+/// Number of bytes read between progress updates while loading a file.
+const PROGRESS_INTERVAL: u64 = 1024 * 1024;
+
+/// Errors that can occur while loading a waveform.
+///
+/// Each variant carries the path of the file, and the underlying error as its source.
+#[derive(Debug, Error)]
+pub enum LoadError {
+    #[error("`{}` is not a VCD or FST file", .0.display())]
+    Unsupported(PathBuf),
+
+    #[error("Unable to read `{}`", .0.display())]
+    Read(PathBuf, #[source] std::io::Error),
+
+    #[error("Unable to parse `{}` as a VCD", .0.display())]
+    Parse(PathBuf, #[source] std::io::Error),
+
+    #[error("Unable to read `{}` as an FST", .0.display())]
+    Fst(PathBuf, #[source] fst::Error),
+}
+
+/// The result of loading a waveform.
+pub type Result<T, E = LoadError> = std::result::Result<T, E>;
+
+/// How to load a waveform file with [`Waveform::load_path_with`].
+#[derive(Clone, Debug, Default)]
+pub struct LoadOptions {
+    /// Load at most this many timestamps, or the whole file.
+    pub limit: Option<u64>,
+
+    /// Receives the number of bytes read, along with the file size, while the file loads.
+    pub progress: Option<Sender<(u64, u64)>>,
+}
+
+/// Signals and their values, whatever format they were read from.
+pub trait WaveformSource: fmt::Debug + Send {
+    /// The IDs of all signals, used to query their values.
+    fn signal_ids(&self) -> Vec<String>;
+
+    /// The full dotted name of a signal, e.g. `top.cpu.clk`, or `None` if it does not exist.
+    fn signal_name(&self, id: &str) -> Option<String>;
+
+    /// All timestamps at which values were recorded, in order.
+    ///
+    /// This builds a new list, so callers querying many values keep it.
+    fn timestamps(&self) -> Vec<Timestamp>;
+
+    /// The value of a signal at a timestamp.
+    ///
+    /// Returns `None` if the signal does not exist, or has no value yet.
+    fn value(&self, id: &str, timestamp: Timestamp) -> Option<SignalValue>;
+}
+
+/// A loaded waveform dump.
+#[derive(Debug)]
+pub struct Waveform {
+    db: Box<dyn WaveformSource>,
+
+    // Taken once, since the database builds a new list on every call.
+    timestamps: Vec<Timestamp>,
+
+    header: Header,
+    dumpoff: Vec<Range<i64>>,
+}
+
+/// Metadata declared in the header of a VCD.
+///
+/// Each field is `None` if the VCD does not declare it.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Header {
+    /// When the dump was generated, e.g. `Mon Jan 1 12:00:00 2024`.
+    pub date: Option<String>,
+    /// The tool that generated the dump.
+    pub version: Option<String>,
+    /// The time unit, e.g. `1 ns` or `10 ps`.
+    pub timescale: Option<String>,
+}
+
+/// Finds the `$dumpoff` ranges of a VCD, from its text in chunks of any size.
+///
+/// This allows the ranges to be found while the VCD is streamed to the parser, instead of reading
+/// it again.
+#[derive(Debug, Default)]
+pub struct DumpoffScanner {
+    // The incomplete line at the end of the last chunk.
+    line: Vec<u8>,
+
+    // The last timestamp, and the timestamp of a `$dumpoff` without a `$dumpon` yet.
+    time: i64,
+    off_since: Option<i64>,
+
+    ranges: Vec<Range<i64>>,
+}
+
+/// Reports the number of bytes read, along with the total file size, through a channel.
+struct ProgressReader<R> {
+    inner: R,
+    read: u64,
+    reported: u64,
+    total: u64,
+    sender: Option<Sender<(u64, u64)>>,
+}
+
+/// Passes the VCD through to the parser, keeping its header and scanning it for `$dumpoff` ranges
+/// on the way.
+struct ScanReader<'a, R> {
+    inner: R,
+    scanner: &'a mut DumpoffScanner,
+
+    // The VCD read so far, until it contains `$enddefinitions`.
+    header: &'a mut Vec<u8>,
+    header_read: bool,
+}
+
+/// Passes the VCD through a line at a time, ending it before the timestamp after `limit`.
+struct LimitReader<R> {
+    inner: R,
+    limit: u64,
+    timestamps: u64,
+    line: Vec<u8>,
+    pos: usize,
+}
+
+/// A signal in a waveform.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Signal {
+    /// Identifier used to query values.
+    pub id: String,
+    /// Full dotted name, e.g. `top.cpu.clk`.
+    pub name: String,
+}
+
+impl Waveform {
+    /// Load a VCD (optionally gzip-compressed) or FST file.
+    pub fn load_path(path: impl AsRef<Path>) -> Result<Self> {
+        Self::load_path_with(path, LoadOptions::default())
+    }
+
+    /// Load a VCD (optionally gzip-compressed) or FST file, streaming it from disk.
+    ///
+    /// FST and gzip files are detected by extension or magic bytes. A gzip-compressed VCD is
+    /// decompressed while streaming.
+    pub fn load_path_with(path: impl AsRef<Path>, options: LoadOptions) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|err| LoadError::Read(path.into(), err))?;
+        let total = file.metadata().map_or(0, |metadata| metadata.len());
+        let mut reader = BufReader::new(ProgressReader {
+            inner: file,
+            read: 0,
+            reported: 0,
+            total,
+            sender: options.progress,
+        });
+
+        let magic = reader
+            .fill_buf()
+            .map_err(|err| LoadError::Read(path.into(), err))?;
+        if fst::is_fst(path, magic) {
+            // FST requires random access, so it is read without progress reporting
+            let mut file = reader.into_inner().inner;
+            file.rewind()
+                .map_err(|err| LoadError::Read(path.into(), err))?;
+            let (source, header) = fst::read(BufReader::new(file), options.limit)
+                .map_err(|err| LoadError::Fst(path.into(), err))?;
+
+            return Ok(Self::new(Box::new(source), header, Vec::new()));
+        }
+
+        if is_gzip(path, magic) {
+            Self::parse(path, BufReader::new(GzDecoder::new(reader)), options.limit)
+        } else {
+            Self::parse(path, reader, options.limit)
+        }
+    }
+
+    /// Load a waveform from a reader, e.g. standard input.
+    ///
+    /// `path` is only used to detect the format by its extension and to describe errors. An FST is
+    /// read into memory first, since it can't be read without random access.
+    pub fn from_reader(path: impl AsRef<Path>, reader: impl Read) -> Result<Self> {
+        let path = path.as_ref();
+        let mut reader = BufReader::new(reader);
+
+        let magic = reader
+            .fill_buf()
+            .map_err(|err| LoadError::Read(path.into(), err))?;
+        if fst::is_fst(path, magic) {
+            let mut buf = Vec::new();
+            reader
+                .read_to_end(&mut buf)
+                .map_err(|err| LoadError::Read(path.into(), err))?;
+            let (source, header) = fst::read(Cursor::new(buf), None)
+                .map_err(|err| LoadError::Fst(path.into(), err))?;
+
+            return Ok(Self::new(Box::new(source), header, Vec::new()));
+        }
+
+        if is_gzip(path, magic) {
+            Self::parse(path, BufReader::new(GzDecoder::new(reader)), None)
+        } else {
+            Self::parse(path, reader, None)
+        }
+    }
+
+    /// Parse a VCD that is known not to be compressed, or an FST.
+    ///
+    /// `path` is only used to describe errors, so any text can be given, e.g. a network address.
+    pub(crate) fn from_vcd(path: impl AsRef<Path>, reader: impl BufRead) -> Result<Self> {
+        Self::parse(path.as_ref(), reader, None)
+    }
+
+    /// Parse a VCD in a single pass, ending it after `limit` timestamps.
+    fn parse(path: &Path, reader: impl BufRead, limit: Option<u64>) -> Result<Self> {
+        let mut scanner = DumpoffScanner::default();
+        let mut header = Vec::new();
+        let db = match limit {
+            Some(limit) => SignalDB::from_vcd(BufReader::new(ScanReader::new(
+                LimitReader::new(reader, limit),
+                &mut scanner,
+                &mut header,
+            ))),
+            None => SignalDB::from_vcd(BufReader::new(ScanReader::new(
+                reader,
+                &mut scanner,
+                &mut header,
+            ))),
+        }
+        .map_err(|err| LoadError::Parse(path.into(), err))?;
+
+        Ok(Self::new(
+            Box::new(db),
+            Header::parse(&header[..]),
+            scanner.finish(),
+        ))
+    }
+
+    /// Combine the signals of a waveform with the header and the `$dumpoff` ranges of the file
+    /// they were read from.
+    pub fn new(db: Box<dyn WaveformSource>, header: Header, dumpoff: Vec<Range<i64>>) -> Self {
+        Self {
+            timestamps: db.timestamps(),
+            db,
+            header,
+            dumpoff,
+        }
+    }
+
+    /// All signals in the waveform.
+    pub fn signals(&self) -> Vec<Signal> {
+        self.db
+            .signal_ids()
+            .into_iter()
+            .map(|id| {
+                let name = self.db.signal_name(&id).unwrap_or_else(|| id.clone());
+
+                Signal { id, name }
+            })
+            .collect()
+    }
+
+    /// The value of a signal at the given time.
+    ///
+    /// Returns `None` if the signal does not exist, or the time is before the first timestamp.
+    pub fn value_at(&self, id: &str, time: i64) -> Option<SignalValue> {
+        let index = self.timestamps.partition_point(|ts| ts.get_value() <= time);
+        let ts = *self.timestamps.get(index.checked_sub(1)?)?;
+
+        self.db.value(id, ts)
+    }
+
+    /// The first and last timestamps, or `None` if no value changes were recorded.
+    pub fn time_range(&self) -> Option<RangeInclusive<i64>> {
+        let first = self.timestamps.first().map(|ts| ts.get_value())?;
+        let last = self.timestamps.last().map(|ts| ts.get_value())?;
+
+        Some(first..=last)
+    }
+
+    /// All timestamps, in order.
+    pub fn timestamps(&self) -> &[Timestamp] {
+        &self.timestamps
+    }
+
+    /// Metadata declared in the VCD header.
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Time ranges in which values were not recorded, between `$dumpoff` and `$dumpon`.
+    ///
+    /// Signals are unknown during these ranges, rather than holding their last value.
+    pub fn dumpoff(&self) -> &[Range<i64>] {
+        &self.dumpoff
+    }
+
+    /// The underlying signals.
+    pub fn source(&self) -> &dyn WaveformSource {
+        self.db.as_ref()
+    }
+
+    /// Take the underlying signals, dropping the header and the `$dumpoff` ranges.
+    pub fn into_source(self) -> Box<dyn WaveformSource> {
+        self.db
+    }
+
+    /// Split the waveform into its signals, header, and `$dumpoff` ranges.
+    pub fn into_parts(self) -> (Box<dyn WaveformSource>, Header, Vec<Range<i64>>) {
+        (self.db, self.header, self.dumpoff)
+    }
+}
+
+impl From<SignalDB> for Waveform {
+    fn from(db: SignalDB) -> Self {
+        Self::new(Box::new(db), Header::default(), Vec::new())
+    }
+}
+
+impl WaveformSource for SignalDB {
+    fn signal_ids(&self) -> Vec<String> {
+        self.get_signal_ids()
+    }
+
+    fn signal_name(&self, id: &str) -> Option<String> {
+        self.get_signal_fullname(id).ok()
+    }
+
+    fn timestamps(&self) -> Vec<Timestamp> {
+        self.get_timestamps()
+    }
+
+    fn value(&self, id: &str, timestamp: Timestamp) -> Option<SignalValue> {
+        self.value_at(id, timestamp).ok()
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.read += len as u64;
+
+        if self.read - self.reported >= PROGRESS_INTERVAL || len == 0 {
+            self.reported = self.read;
+
+            // The receiver is gone if the UI no longer cares about progress
+            if let Some(sender) = &self.sender {
+                let _ = sender.send((self.read, self.total));
+            }
+        }
+
+        Ok(len)
+    }
+}
+
+impl<'a, R> ScanReader<'a, R> {
+    fn new(inner: R, scanner: &'a mut DumpoffScanner, header: &'a mut Vec<u8>) -> Self {
+        Self {
+            inner,
+            scanner,
+            header,
+            header_read: false,
+        }
+    }
+}
+
+impl<R: Read> Read for ScanReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.scanner.scan(&buf[..len]);
+
+        // The keyword may be split across reads
+        if !self.header_read {
+            let start = self.header.len().saturating_sub(END_DEFINITIONS.len());
+            self.header.extend_from_slice(&buf[..len]);
+            self.header_read = contains(&self.header[start..], END_DEFINITIONS);
+        }
+
+        Ok(len)
+    }
+}
+
+impl<R> LimitReader<R> {
+    fn new(inner: R, limit: u64) -> Self {
+        Self {
+            inner,
+            limit,
+            timestamps: 0,
+            line: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Read for LimitReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos == self.line.len() {
+            self.line.clear();
+            self.pos = 0;
+            if self.timestamps > self.limit || self.inner.read_until(b'\n', &mut self.line)? == 0 {
+                return Ok(0);
+            }
+
+            // Timestamps are the only lines that start with `#`
+            let start = self
+                .line
+                .iter()
+                .position(|byte| !byte.is_ascii_whitespace());
+            if start.is_some_and(|start| self.line[start] == b'#') {
+                self.timestamps += 1;
+                if self.timestamps > self.limit {
+                    self.line.clear();
+                    return Ok(0);
+                }
+            }
+        }
+
+        let len = buf.len().min(self.line.len() - self.pos);
+        buf[..len].copy_from_slice(&self.line[self.pos..self.pos + len]);
+        self.pos += len;
+
+        Ok(len)
+    }
+}
+
+impl DumpoffScanner {
+    /// Scan the next chunk of the VCD.
+    pub fn scan(&mut self, bytes: &[u8]) {
+        for chunk in bytes.split_inclusive(|&byte| byte == b'\n') {
+            self.line.extend_from_slice(chunk);
+            if chunk.ends_with(b"\n") {
+                let line = std::mem::take(&mut self.line);
+                self.scan_line(&line);
+                self.line = line;
+                self.line.clear();
+            }
+        }
+    }
+
+    /// The ranges found in the VCD.
+    ///
+    /// A `$dumpoff` without a `$dumpon` lasts until the last timestamp.
+    pub fn finish(mut self) -> Vec<Range<i64>> {
+        let line = std::mem::take(&mut self.line);
+        self.scan_line(&line);
+        if let Some(start) = self.off_since.take() {
+            self.ranges.push(start..self.time + 1);
+        }
+
+        self.ranges
+    }
+
+    fn scan_line(&mut self, line: &[u8]) {
+        let line = String::from_utf8_lossy(line);
+        let mut words = line.split_whitespace();
+
+        // Identifiers may begin with `#`, so only the first word can be a timestamp
+        let first = words.next();
+        if let Some(time) = first.and_then(|word| word.strip_prefix('#')?.parse().ok()) {
+            self.time = time;
+        }
+
+        for word in first.into_iter().chain(words) {
+            match word {
+                "$dumpoff" if self.off_since.is_none() => self.off_since = Some(self.time),
+                "$dumpon" => {
+                    let range = self.off_since.take().map(|start| start..self.time);
+                    self.ranges.extend(range.filter(|range| !range.is_empty()));
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+impl Header {
+    /// Read the `$date`, `$version`, and `$timescale` declarations of a VCD.
+    ///
+    /// Reading stops at `$enddefinitions`, so the value changes are not read. Declarations that
+    /// could not be read are left as `None`.
+    pub fn parse(reader: impl BufRead) -> Self {
+        let mut header = Self::default();
+
+        // The declaration being read, and its words so far
+        let mut declaration: Option<(String, Vec<String>)> = None;
+        for line in reader.lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+
+            for word in line.split_whitespace() {
+                match declaration.as_mut() {
+                    Some((keyword, words)) if word == "$end" => {
+                        let text = Some(words.join(" ")).filter(|text| !text.is_empty());
+                        match keyword.as_str() {
+                            "$date" => header.date = text,
+                            "$version" => header.version = text,
+                            "$timescale" => header.timescale = text,
+                            _ => (),
+                        }
+                        declaration = None;
+                    }
+                    Some((_, words)) => words.push(word.to_string()),
+                    None if word == "$enddefinitions" => return header,
+                    None if word.starts_with('$') => {
+                        declaration = Some((word.to_string(), Vec::new()));
+                    }
+                    None => (),
+                }
+            }
+        }
+
+        header
+    }
+}
+
+/// Whether a file is gzip-compressed, by its extension or its first bytes.
+fn is_gzip(path: &Path, magic: &[u8]) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gz"))
+        || magic.starts_with(&GZIP_MAGIC)
+}
+
+/// Whether `bytes` contains `pattern`.
+pub(crate) fn contains(bytes: &[u8], pattern: &[u8]) -> bool {
+    bytes.windows(pattern.len()).any(|window| window == pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::radix::Radix;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write as _;
+
+    const VCD: &str = "$date\n\
+                       \tMon Jan 1 12:00:00 2024\n\
+                       $end\n\
+                       $version Icarus Verilog $end\n\
+                       $timescale\n\
+                       \t10 ps\n\
+                       $end\n\
+                       $scope module top $end\n\
+                       $var wire 1 ! clk $end\n\
+                       $upscope $end\n\
+                       $enddefinitions $end\n\
+                       #5\n\
+                       0!\n\
+                       #10\n\
+                       1!\n";
+
+    /// The value of the only signal in `waveform` at `time`, in binary.
+    fn value_at(waveform: &Waveform, time: i64) -> Option<String> {
+        let id = &waveform.signals()[0].id;
+
+        waveform
+            .value_at(id, time)
+            .map(|value| Radix::Binary.format_value(&value))
+    }
+
+    #[test]
+    fn parse_header() {
+        let header = Header::parse(VCD.as_bytes());
+
+        assert_eq!(
+            header,
+            Header {
+                date: Some("Mon Jan 1 12:00:00 2024".to_string()),
+                version: Some("Icarus Verilog".to_string()),
+                timescale: Some("10 ps".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_header_without_timescale() {
+        let vcd = "$version gen $end\n\
+                   $enddefinitions $end\n\
+                   $timescale 1 ns $end\n";
+        let header = Header::parse(vcd.as_bytes());
+
+        // Declarations after the header are not read
+        assert_eq!(header.version.as_deref(), Some("gen"));
+        assert_eq!(header.timescale, None);
+        assert_eq!(header.date, None);
+    }
+
+    #[test]
+    fn scan_dumpoff_in_chunks() {
+        let vcd = "#0\n1!\n#10\n$dumpoff\nx!\n$end\n#25\n$dumpon\n1!\n$end\n#30\n$dumpoff\n#40\n";
+
+        // Every split leaves some lines across two chunks
+        for size in 1..vcd.len() {
+            let mut scanner = DumpoffScanner::default();
+            for chunk in vcd.as_bytes().chunks(size) {
+                scanner.scan(chunk);
+            }
+
+            // The second `$dumpoff` is never closed, so it lasts until the last timestamp
+            assert_eq!(scanner.finish(), [10..25, 30..41], "chunks of {size}");
+        }
+    }
+
+    #[test]
+    fn scan_dumpoff_without_newline() {
+        let mut scanner = DumpoffScanner::default();
+        scanner.scan(b"#0\n1!\n#7\n$dumpoff");
+
+        assert_eq!(scanner.finish(), [7..8]);
+    }
+
+    #[test]
+    fn limit_timestamps() {
+        let vcd = "$enddefinitions $end\n#0\n0!\n  #5\n1!\n#10\n0!\n";
+        let limits = [
+            (0, "$enddefinitions $end\n"),
+            (1, "$enddefinitions $end\n#0\n0!\n"),
+            (2, "$enddefinitions $end\n#0\n0!\n  #5\n1!\n"),
+            (3, vcd),
+            (10, vcd),
+        ];
+
+        for (limit, expected) in limits {
+            let mut text = String::new();
+            LimitReader::new(vcd.as_bytes(), limit)
+                .read_to_string(&mut text)
+                .unwrap();
+
+            assert_eq!(text, expected, "{limit}");
+        }
+    }
+
+    #[test]
+    fn limit_into_small_buffer() {
+        let vcd = "#0\n0!\n#5\n1!\n";
+        let mut reader = LimitReader::new(vcd.as_bytes(), 1);
+
+        // Lines longer than the buffer are passed through in pieces
+        let mut text = Vec::new();
+        let mut buf = [0; 1];
+        while let Ok(len @ 1..) = reader.read(&mut buf) {
+            text.extend_from_slice(&buf[..len]);
+        }
+
+        assert_eq!(text, b"#0\n0!\n");
+    }
+
+    #[test]
+    fn load_gzip_by_magic() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(VCD.as_bytes()).unwrap();
+        let gzip = encoder.finish().unwrap();
+
+        // The path has no extension, so only the magic number identifies the compression
+        let waveform = Waveform::from_reader("dump", &gzip[..]).unwrap();
+
+        assert_eq!(waveform.time_range(), Some(5..=10));
+        assert_eq!(waveform.header().timescale.as_deref(), Some("10 ps"));
+        assert_eq!(value_at(&waveform, 12).as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn value_at_times() {
+        let waveform = Waveform::from_reader("dump.vcd", VCD.as_bytes()).unwrap();
+        let times = [
+            (0, None),
+            (4, None),
+            (5, Some("0")),
+            (9, Some("0")),
+            (10, Some("1")),
+            (100, Some("1")),
+        ];
+
+        for (time, expected) in times {
+            assert_eq!(value_at(&waveform, time).as_deref(), expected, "{time}");
+        }
+        assert_eq!(waveform.value_at("missing", 10), None);
+    }
+}